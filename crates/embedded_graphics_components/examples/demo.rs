@@ -3,7 +3,15 @@ use embedded_graphics::{
     prelude::{Dimensions, Point, Size},
 };
 use embedded_graphics_components::{
-    battery_indicator::BatteryIndicator, schedule_table::ScheduleTable, unified_color::UnifiedColor,
+    battery_indicator::BatteryIndicator,
+    component::Component,
+    event::Event,
+    layout::{Length, Stack},
+    schedule_table::ScheduleTable,
+    screen::{Node, ScreenData},
+    theme::Theme,
+    time_grid::{SlotLength, TimeGrid},
+    unified_color::UnifiedColor,
 };
 use embedded_graphics_simulator::{
     BinaryColorTheme, OutputSettingsBuilder, SimulatorDisplay, Window,
@@ -16,86 +24,78 @@ fn main() -> anyhow::Result<()> {
     // // Simulate the drawing process
     // display.set_rotation(epd_waveshare::prelude::DisplayRotation::Rotate90);
 
-    // Get display dimensions for calculations
-    let display_width = display.bounding_box().size.width;
-    let display_height = display.bounding_box().size.height;
-
-    // --- ScheduleTable parameters ---
-    let header_height = 40;
-    let time_col_width = 80;
-    let num_date_cols = 3;
-    // Number of data rows: this now controls how many rows are allocated visually.
-    // Ensure this value is chosen such that `(display_height - header_height)` is divisible by `num_data_rows`
-    // to avoid rounding issues if you want perfect pixel alignment.
-    // For a 128px height display with 40px header, you have 88px left.
-    // 88 / 12 = 7.33, so 12 is problematic.
-    // Let's re-evaluate for clean division: if display_height is 128 and header_height is 40,
-    // we have 88px for rows.
-    // If you want to show, say, 11 hours (6 to 17), that's 11 rows. 88 / 11 = 8px per hour.
-    // If you want to show 12 hours (6 to 18), 88 / 12 = 7.33. Let's stick with 12 if you desire that range,
-    // and accept potential rounding that `embedded-graphics` handles.
-    // Or adjust range, or adjust header_height/total_height to make it divisible.
-    // For simplicity with given values, we'll keep num_data_rows = 12 as per original table height scaling.
-    let num_data_rows = 12;
-
-    let battery_bar_height: u32 = 10; // Высота полосы батареи внизу
-
-    let y_pos_offset = 10;
-    let nowline_time = 13.5;
-
-    let header_texts = ["Time", "01.01.2025", "02.01.2025", "03.01.2025"];
-    let time_range = 6..=17; // From 6:00 to 18:00, which is 13 hours/rows effectively
+    let screen = display.bounding_box();
+    let converter = |color: UnifiedColor| match color {
+        UnifiedColor::Black => Rgb565::new(0, 0, 0),
+        UnifiedColor::White => Rgb565::new(255, 255, 255),
+        UnifiedColor::Chromatic => Rgb565::new(255, 0, 0),
+    };
 
     let time_intervals = [
-        ("01.01.2025", 6.0, 12.25, "xsichkaruk"),
-        ("01.01.2025", 12.5, 14.0, "xchaban"),
-        ("01.01.2025", 14.5, 17.0, "xchaban"),
-        ("02.01.2025", 10.25, 10.75, "xchaban"),
-        ("02.01.2025", 11.5, 13.25, "xtodorov"),
-        ("02.01.2025", 13.5, 15.0, "xchaban"),
-        ("03.01.2025", 10.0, 12.0, "xchaban"),
-        ("03.01.2025", 12.25, 14.5, "xchaban"),
-        ("03.01.2025", 15.0, 16.0, "xchaban"),
-        ("01.01.2025", 17.0, 17.25, "xchaban"),
-        ("02.01.2025", 17.0, 17.5, "xchaban"),
-        ("03.01.2025", 17.0, 18.00, "xchaban"),
+        Event::new("01.01.2025", 6.0, 12.25, "xsichkaruk"),
+        Event::new("01.01.2025", 12.5, 14.0, "xchaban"),
+        Event::new("01.01.2025", 14.5, 17.0, "xchaban"),
+        Event::new("02.01.2025", 10.25, 10.75, "xchaban"),
+        Event::new("02.01.2025", 11.5, 13.25, "xtodorov"),
+        Event::new("02.01.2025", 13.5, 15.0, "xchaban"),
+        Event::new("03.01.2025", 10.0, 12.0, "xchaban"),
+        Event::new("03.01.2025", 12.25, 14.5, "xchaban"),
+        Event::new("03.01.2025", 15.0, 16.0, "xchaban"),
+        Event::new("01.01.2025", 17.0, 17.25, "xchaban"),
+        Event::new("02.01.2025", 17.0, 17.5, "xchaban"),
+        Event::new("03.01.2025", 17.0, 18.00, "xchaban"),
     ];
 
-    ScheduleTable::new(
-        Point::new(0, battery_bar_height as i32), // Table starts at top-left of the display
-        Size::new(display_width, display_height - battery_bar_height), // Table occupies full display
-        header_height,
-        time_col_width,
-        num_date_cols,
-        num_data_rows, // Use dynamic row count
-        y_pos_offset,
-        nowline_time,
-        header_texts,
-        time_range,
-        time_intervals,
-        |color| match color {
-            UnifiedColor::Black => Rgb565::new(0, 0, 0),
-            UnifiedColor::White => Rgb565::new(255, 255, 255),
-            UnifiedColor::Chromatic => Rgb565::new(255, 0, 0),
-        },
-    )
-    .draw(&mut display)?;
-
-    let battery_level_percent = 19;
-
-    BatteryIndicator::new(
-        Point::new(0, 0),
-        Size::new(display_width, battery_bar_height),
-        |color| match color {
-            UnifiedColor::Black => Rgb565::new(0, 0, 0),
-            UnifiedColor::White => Rgb565::new(255, 255, 255),
-            UnifiedColor::Chromatic => Rgb565::new(255, 0, 0),
-        },
-    )
-    .draw(&mut display, battery_level_percent)?;
+    // `cargo run --example demo -- screen.json [logo.bmp]` previews a layout
+    // description the way the firmware renders it from NVS.
+    if let Some(path) = std::env::args().nth(1) {
+        let node: Node = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let logo = std::env::args().nth(2).map(std::fs::read).transpose()?;
+        let data = ScreenData {
+            events: &time_intervals,
+            dates: &["01.01.2025", "02.01.2025", "03.01.2025"],
+            today: "02.01.2025",
+            now: 13.5,
+            room_name: "Everest",
+            battery_percent: 19,
+            logo: logo.as_deref(),
+            booking_url: Some("https://example.com/book/everest"),
+        };
+        node.build(screen, &data, Theme::for_size(screen.size), converter)?
+            .draw_in(&mut display, screen)?;
+    } else {
+        draw_default(&mut display, &time_intervals, converter)?;
+    }
 
     let output_settings = OutputSettingsBuilder::new().scale(2).build();
     Window::new("Hello World", &output_settings).show_static(&display);
 
     Ok(())
 }
+
+fn draw_default(
+    display: &mut SimulatorDisplay<Rgb565>,
+    time_intervals: &[Event],
+    converter: impl Fn(UnifiedColor) -> Rgb565 + Copy,
+) -> anyhow::Result<()> {
+    let screen = display.bounding_box();
+    let table = ScheduleTable::builder(screen.top_left, screen.size, converter)
+        .header_height(40)
+        .time_col_width(80)
+        .dates(&["01.01.2025", "02.01.2025", "03.01.2025"])
+        .now(13.5)
+        .time_grid(TimeGrid::hours(6, 18, SlotLength::Min60)) // 6:00 to 18:00, one row per hour
+        .events(time_intervals)
+        .build()?;
+
+    let battery = BatteryIndicator::new(Point::zero(), Size::new(screen.size.width, 10), converter)
+        .with_level(19);
+
+    // Battery bar on top, the table takes the rest
+    Stack::vertical()
+        .child(Length::Auto, battery)
+        .child(Length::Flex(1), table)
+        .draw_in(display, screen)?;
+
+    Ok(())
+}
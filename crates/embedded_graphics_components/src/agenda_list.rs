@@ -0,0 +1,280 @@
+use std::marker::PhantomData;
+
+use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Line, PrimitiveStyleBuilder, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use crate::component::Component;
+use crate::event::{Event, chronological, format_time};
+use crate::text::truncate;
+use crate::theme::Theme;
+use crate::unified_color::UnifiedColor;
+
+/// `"10:00-11:30 "`
+const TIME_RANGE_CHARS: i32 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Row<'e, 'a> {
+    /// Day header, `continued` when the day started on an earlier page.
+    Day {
+        date: &'a str,
+        continued: bool,
+    },
+    Event(&'e Event<'a>),
+}
+
+impl Row<'_, '_> {
+    fn height(&self, theme: &Theme) -> i32 {
+        match self {
+            Row::Day { .. } => theme.font_height() + 2 * theme.padding,
+            Row::Event(event) => {
+                let organizer = if event.organizer.is_some() {
+                    theme.small_font_height()
+                } else {
+                    0
+                };
+                theme.font_height() + organizer + theme.padding
+            }
+        }
+    }
+}
+
+/// Events as a chronological list under day headers. What doesn't fit goes
+/// to further pages, each page but the last ends with "+N more".
+#[derive(Clone)]
+pub struct AgendaList<'a, F, C> {
+    top_left: Point,
+    size: Size,
+    events: &'a [Event<'a>],
+    theme: Theme,
+
+    color_converter: F,
+    _phantom_color: PhantomData<C>,
+}
+
+impl<'a, F, C> AgendaList<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+{
+    pub fn new(top_left: Point, size: Size, events: &'a [Event<'a>], color_converter: F) -> Self {
+        AgendaList {
+            top_left,
+            size,
+            events,
+            theme: Theme::default(),
+            color_converter,
+            _phantom_color: PhantomData,
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    fn footer_height(&self) -> i32 {
+        self.theme.small_font_height() + self.theme.padding
+    }
+
+    /// Rows split into pages. A day header never ends a page, and a day
+    /// split across pages gets its header repeated.
+    pub fn pages(&self) -> Vec<Vec<Row<'a, 'a>>> {
+        let available = self.size.height as i32 - self.theme.padding - self.footer_height();
+        let mut pages = vec![Vec::new()];
+        let mut used = 0;
+        let mut current_date = None;
+
+        for event in chronological(self.events) {
+            let mut rows = Vec::new();
+            if current_date != Some(event.date) {
+                current_date = Some(event.date);
+                rows.push(Row::Day {
+                    date: event.date,
+                    continued: false,
+                });
+            }
+            rows.push(Row::Event(event));
+
+            let needed: i32 = rows.iter().map(|row| row.height(&self.theme)).sum();
+            let page = pages.last_mut().unwrap();
+            if used + needed > available && !page.is_empty() {
+                if rows.len() == 1 {
+                    rows.insert(
+                        0,
+                        Row::Day {
+                            date: event.date,
+                            continued: true,
+                        },
+                    );
+                }
+                pages.push(Vec::new());
+                used = 0;
+            }
+
+            used += rows.iter().map(|row| row.height(&self.theme)).sum::<i32>();
+            pages.last_mut().unwrap().extend(rows);
+        }
+
+        pages
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages().len()
+    }
+
+    /// Draws the first page.
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        self.draw_page(display, 0)
+    }
+
+    pub fn draw_page<D>(&self, display: &mut D, page: usize) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let theme = &self.theme;
+        let font_width = theme.font_width();
+        let font_height = theme.font_height();
+        let small_font_width = theme.small_font_width();
+        let padding = theme.padding;
+        let width = self.size.width as i32;
+        let left = self.top_left.x + padding;
+        let text_width = width - 2 * padding;
+
+        Rectangle::new(self.top_left, self.size)
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(UnifiedColor::White.into_with(self.color_converter))
+                    .build(),
+            )
+            .draw(display)?;
+
+        let text_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.font)
+            .text_color(UnifiedColor::Black.into_with(self.color_converter))
+            .build();
+        let text_small_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.small_font)
+            .text_color(UnifiedColor::Black.into_with(self.color_converter))
+            .build();
+        let header_style: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.font)
+            .text_color(Theme::accent(theme.accents.headings).into_with(self.color_converter))
+            .build();
+        let line_style = PrimitiveStyleBuilder::new()
+            .stroke_color(UnifiedColor::Black.into_with(self.color_converter))
+            .stroke_width(theme.line_width)
+            .build();
+
+        let pages = self.pages();
+        let Some(rows) = pages.get(page) else {
+            return Ok(());
+        };
+
+        let mut y = self.top_left.y + padding;
+        for row in rows {
+            match *row {
+                Row::Day { date, continued } => {
+                    let header = if continued {
+                        format!("{date} (cont.)")
+                    } else {
+                        date.to_string()
+                    };
+                    Text::with_baseline(
+                        &header,
+                        Point::new(left, y + padding),
+                        header_style,
+                        Baseline::Top,
+                    )
+                    .draw(display)?;
+                    let line_y = y + font_height + padding + padding / 2;
+                    Line::new(
+                        Point::new(left, line_y),
+                        Point::new(left + text_width - 1, line_y),
+                    )
+                    .into_styled(line_style)
+                    .draw(display)?;
+                }
+                Row::Event(event) => {
+                    let range = format!("{}-{}", format_time(event.start), format_time(event.end));
+                    Text::with_baseline(
+                        &range,
+                        Point::new(left, y),
+                        text_style_black,
+                        Baseline::Top,
+                    )
+                    .draw(display)?;
+
+                    let title_x = left + TIME_RANGE_CHARS * font_width;
+                    let title_chars =
+                        ((text_width - TIME_RANGE_CHARS * font_width) / font_width).max(1) as usize;
+                    Text::with_baseline(
+                        &truncate(event.title, title_chars),
+                        Point::new(title_x, y),
+                        text_style_black,
+                        Baseline::Top,
+                    )
+                    .draw(display)?;
+
+                    if let Some(organizer) = event.organizer {
+                        let organizer_chars = ((text_width - TIME_RANGE_CHARS * font_width)
+                            / small_font_width)
+                            .max(1) as usize;
+                        Text::with_baseline(
+                            &truncate(organizer, organizer_chars),
+                            Point::new(title_x, y + font_height),
+                            text_small_style_black,
+                            Baseline::Top,
+                        )
+                        .draw(display)?;
+                    }
+                }
+            }
+            y += row.height(theme);
+        }
+
+        let more: usize = pages[page + 1..]
+            .iter()
+            .flatten()
+            .filter(|row| matches!(row, Row::Event(_)))
+            .count();
+        if more > 0 {
+            let right = TextStyleBuilder::new()
+                .alignment(Alignment::Right)
+                .baseline(Baseline::Bottom)
+                .build();
+            Text::with_text_style(
+                &format!("+{more} more"),
+                Point::new(
+                    left + text_width,
+                    self.top_left.y + self.size.height as i32 - padding,
+                ),
+                text_small_style_black,
+                right,
+            )
+            .draw(display)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, F, C, D> Component<D> for AgendaList<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        AgendaList {
+            top_left: area.top_left,
+            size: area.size,
+            ..self.clone()
+        }
+        .draw(display)
+    }
+}
@@ -1,17 +1,21 @@
 use std::marker::PhantomData;
 
-use embedded_graphics::mono_font::ascii::FONT_6X10;
 use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::{Line, PrimitiveStyleBuilder, Rectangle};
 use embedded_graphics::text::renderer::TextRenderer;
 use embedded_graphics::text::{Baseline, Text};
 
+use crate::component::Component;
+use crate::theme::Theme;
 use crate::unified_color::UnifiedColor;
 
+#[derive(Clone)]
 pub struct BatteryIndicator<F, C> {
     top_left: Point,
     size: Size,
+    theme: Theme,
+    level: u8,
     // Функция-конвертер цвета
     color_converter: F,
     _phantom_color: PhantomData<C>,
@@ -27,11 +31,29 @@ where
         BatteryIndicator {
             top_left,
             size,
+            theme: Theme::default(),
+            level: 100,
             color_converter,
             _phantom_color: PhantomData,
         }
     }
 
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Charge in percent drawn as a [`Component`].
+    pub fn with_level(mut self, battery_level_percent: u8) -> Self {
+        self.level = battery_level_percent;
+        self
+    }
+
+    /// Area redrawn by [`Self::draw`], dirty whenever the level changes.
+    pub fn area(&self) -> Rectangle {
+        Rectangle::new(self.top_left, self.size)
+    }
+
     pub fn draw<D>(&self, display: &mut D, battery_level_percent: u8) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = C>,
@@ -51,7 +73,7 @@ where
 
         // 3. Определяем цвет заполнения
         let fill_color = if battery_level_percent <= 20 {
-            UnifiedColor::Chromatic.into_with(self.color_converter) // Красный для низкого заряда
+            Theme::accent(self.theme.accents.warnings).into_with(self.color_converter) // Красный для низкого заряда
         } else {
             UnifiedColor::Black.into_with(self.color_converter) // Черный для нормального заряда
         };
@@ -67,8 +89,10 @@ where
         // display power text only if battery level is low
         if battery_level_percent <= 20 {
             let text_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
-                .font(&FONT_6X10)
-                .text_color(UnifiedColor::Chromatic.into_with(self.color_converter))
+                .font(self.theme.small_font)
+                .text_color(
+                    Theme::accent(self.theme.accents.warnings).into_with(self.color_converter),
+                )
                 .build();
 
             let text = format!("{battery_level_percent}%");
@@ -86,3 +110,23 @@ where
         Ok(())
     }
 }
+
+impl<F, C, D> Component<D> for BatteryIndicator<F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    fn measure(&self, available: Size) -> Size {
+        Size::new(available.width, self.size.height.min(available.height))
+    }
+
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        BatteryIndicator {
+            top_left: area.top_left,
+            size: area.size,
+            ..self.clone()
+        }
+        .draw(display, self.level)
+    }
+}
@@ -0,0 +1,34 @@
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+/// Something that draws itself into whatever area a layout gives it. `D`
+/// is a trait parameter so containers can hold `Box<dyn Component<D>>`.
+pub trait Component<D: DrawTarget> {
+    /// Size wanted out of `available`. Most components take all of it.
+    fn measure(&self, available: Size) -> Size {
+        available
+    }
+
+    /// Draws into `area`, wherever the component was created.
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error>;
+}
+
+impl<D: DrawTarget, T: Component<D> + ?Sized> Component<D> for &T {
+    fn measure(&self, available: Size) -> Size {
+        (**self).measure(available)
+    }
+
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        (**self).draw_in(display, area)
+    }
+}
+
+impl<D: DrawTarget, T: Component<D> + ?Sized> Component<D> for Box<T> {
+    fn measure(&self, available: Size) -> Size {
+        (**self).measure(available)
+    }
+
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        (**self).draw_in(display, area)
+    }
+}
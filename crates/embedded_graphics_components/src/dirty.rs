@@ -0,0 +1,272 @@
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+/// Areas of the screen that changed since the last refresh. Overlapping or
+/// touching rectangles are merged, so every window is sent to the panel once.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirtyRegions {
+    rects: Vec<Rectangle>,
+}
+
+impl DirtyRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, area: Rectangle) {
+        if area.is_zero_sized() {
+            return;
+        }
+
+        let mut merged = area;
+        loop {
+            let before = self.rects.len();
+            self.rects.retain(|other| {
+                if touches(&merged, other) {
+                    merged = union(&merged, other);
+                    false
+                } else {
+                    true
+                }
+            });
+            if self.rects.len() == before {
+                break;
+            }
+        }
+        self.rects.push(merged);
+    }
+
+    /// Grows every rectangle to whole multiples of `step` from the origin,
+    /// the windows the panel can refresh on its own, e.g. 8 pixels along
+    /// its byte aligned axis. Windows that meet then are merged.
+    pub fn align(&mut self, step: Size) {
+        let step = Point::new(step.width.max(1) as i32, step.height.max(1) as i32);
+        for rect in std::mem::take(&mut self.rects) {
+            // Not zero sized, so bottom_right exists.
+            let bottom_right = rect.bottom_right().unwrap() + Point::new(1, 1);
+            let top_left = Point::new(
+                rect.top_left.x.div_euclid(step.x) * step.x,
+                rect.top_left.y.div_euclid(step.y) * step.y,
+            );
+            let bottom_right = Point::new(
+                (bottom_right.x + step.x - 1).div_euclid(step.x) * step.x,
+                (bottom_right.y + step.y - 1).div_euclid(step.y) * step.y,
+            );
+            self.add(Rectangle::with_corners(
+                top_left,
+                bottom_right - Point::new(1, 1),
+            ));
+        }
+    }
+
+    /// Drops everything outside `bounds`, e.g. the screen.
+    pub fn clip(&mut self, bounds: Rectangle) {
+        for rect in &mut self.rects {
+            *rect = rect.intersection(&bounds);
+        }
+        self.rects.retain(|rect| !rect.is_zero_sized());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    pub fn rects(&self) -> &[Rectangle] {
+        &self.rects
+    }
+
+    /// Number of dirty pixels.
+    pub fn area(&self) -> u32 {
+        self.rects
+            .iter()
+            .map(|rect| rect.size.width * rect.size.height)
+            .sum()
+    }
+}
+
+fn touches(a: &Rectangle, b: &Rectangle) -> bool {
+    let grown = Rectangle::new(a.top_left - Point::new(1, 1), a.size + Size::new(2, 2));
+    !grown.intersection(b).is_zero_sized()
+}
+
+fn union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    // Both are non-empty, so bottom_right exists.
+    let (a_br, b_br) = (a.bottom_right().unwrap(), b.bottom_right().unwrap());
+    Rectangle::with_corners(
+        a.top_left.component_min(b.top_left),
+        a_br.component_max(b_br),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Refresh {
+    /// Nothing changed.
+    None,
+    /// Refresh only these windows.
+    Partial(Vec<Rectangle>),
+    Full,
+}
+
+/// Decides between partial and full refreshes. Partial refreshes leave
+/// ghosting behind, so every `full_every`-th refresh is a full one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefreshPolicy {
+    pub full_every: u32,
+    /// Above this share of the screen a full refresh is not much slower and
+    /// looks better.
+    pub max_partial_fraction: f32,
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        RefreshPolicy {
+            full_every: 8,
+            max_partial_fraction: 0.5,
+        }
+    }
+}
+
+impl RefreshPolicy {
+    pub fn plan(
+        &self,
+        dirty: &DirtyRegions,
+        screen: Size,
+        supports_partial: bool,
+        partials_since_full: u32,
+    ) -> Refresh {
+        if dirty.is_empty() {
+            return Refresh::None;
+        }
+        let screen_area = (screen.width * screen.height) as f32;
+        if !supports_partial
+            || partials_since_full + 1 >= self.full_every
+            || dirty.area() as f32 > screen_area * self.max_partial_fraction
+        {
+            return Refresh::Full;
+        }
+        Refresh::Partial(dirty.rects().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn regions(rects: &[Rectangle]) -> DirtyRegions {
+        let mut dirty = DirtyRegions::new();
+        for &rect in rects {
+            dirty.add(rect);
+        }
+        dirty
+    }
+
+    #[test]
+    fn merges_overlapping_and_touching() {
+        let dirty = regions(&[rect(0, 0, 10, 10), rect(5, 5, 10, 10)]);
+        assert_eq!(dirty.rects(), [rect(0, 0, 15, 15)]);
+
+        // Edge to edge, and corner to corner
+        let dirty = regions(&[rect(0, 0, 10, 10), rect(10, 0, 5, 10)]);
+        assert_eq!(dirty.rects(), [rect(0, 0, 15, 10)]);
+        let dirty = regions(&[rect(0, 0, 10, 10), rect(10, 10, 5, 5)]);
+        assert_eq!(dirty.rects(), [rect(0, 0, 15, 15)]);
+
+        // One pixel apart stays separate
+        let dirty = regions(&[rect(0, 0, 10, 10), rect(11, 0, 5, 10)]);
+        assert_eq!(dirty.rects(), [rect(0, 0, 10, 10), rect(11, 0, 5, 10)]);
+        assert_eq!(dirty.area(), 150);
+    }
+
+    #[test]
+    fn merges_transitively() {
+        // The last one bridges the first two, and the union then reaches the third
+        let dirty = regions(&[
+            rect(0, 0, 4, 4),
+            rect(10, 0, 4, 4),
+            rect(5, 4, 2, 2),
+            rect(3, 0, 8, 2),
+        ]);
+        assert_eq!(dirty.rects(), [rect(0, 0, 14, 6)]);
+    }
+
+    #[test]
+    fn ignores_empty_and_clips() {
+        let mut dirty = regions(&[rect(5, 5, 0, 10), rect(-5, 90, 20, 20), rect(200, 0, 5, 5)]);
+        assert_eq!(dirty.rects().len(), 2);
+
+        dirty.clip(rect(0, 0, 100, 100));
+        assert_eq!(dirty.rects(), [rect(0, 90, 15, 10)]);
+        dirty.clip(rect(50, 0, 50, 50));
+        assert!(dirty.is_empty());
+    }
+
+    #[test]
+    fn aligns_to_refresh_windows() {
+        let mut dirty = regions(&[rect(3, 5, 6, 2)]);
+        dirty.align(Size::new(8, 1));
+        assert_eq!(dirty.rects(), [rect(0, 5, 16, 2)]);
+
+        // Already aligned, and negative coordinates round outward too
+        let mut dirty = regions(&[rect(8, 0, 8, 3), rect(-3, 20, 2, 1)]);
+        dirty.align(Size::new(8, 1));
+        assert_eq!(dirty.rects(), [rect(8, 0, 8, 3), rect(-8, 20, 8, 1)]);
+
+        // Rotated panels align the other axis; windows that now overlap merge
+        let mut dirty = regions(&[rect(0, 1, 50, 2), rect(0, 12, 50, 2)]);
+        dirty.align(Size::new(1, 8));
+        assert_eq!(dirty.rects(), [rect(0, 0, 50, 16)]);
+
+        let mut dirty = regions(&[rect(1, 1, 1, 1)]);
+        dirty.align(Size::zero());
+        assert_eq!(dirty.rects(), [rect(1, 1, 1, 1)]);
+    }
+
+    #[test]
+    fn plans_refreshes() {
+        let policy = RefreshPolicy::default();
+        let screen = Size::new(100, 100);
+        let band = regions(&[rect(0, 40, 100, 10)]);
+
+        assert_eq!(
+            policy.plan(&DirtyRegions::new(), screen, true, 0),
+            Refresh::None
+        );
+        assert_eq!(
+            policy.plan(&band, screen, true, 0),
+            Refresh::Partial(vec![rect(0, 40, 100, 10)])
+        );
+        assert_eq!(policy.plan(&band, screen, false, 0), Refresh::Full);
+    }
+
+    #[test]
+    fn full_refresh_after_partials() {
+        let policy = RefreshPolicy {
+            full_every: 4,
+            ..RefreshPolicy::default()
+        };
+        let band = regions(&[rect(0, 40, 100, 10)]);
+        let plans: Vec<bool> = (0..5)
+            .map(|partials| {
+                policy.plan(&band, Size::new(100, 100), true, partials) == Refresh::Full
+            })
+            .collect();
+        assert_eq!(plans, [false, false, false, true, true]);
+    }
+
+    #[test]
+    fn full_refresh_for_large_areas() {
+        let policy = RefreshPolicy::default();
+        let screen = Size::new(100, 100);
+        let half = regions(&[rect(0, 0, 100, 50)]);
+        assert!(matches!(
+            policy.plan(&half, screen, true, 0),
+            Refresh::Partial(_)
+        ));
+        let more = regions(&[rect(0, 0, 100, 50), rect(0, 60, 1, 1)]);
+        assert_eq!(policy.plan(&more, screen, true, 0), Refresh::Full);
+    }
+}
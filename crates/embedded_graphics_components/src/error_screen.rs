@@ -0,0 +1,230 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyleBuilder, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use crate::component::Component;
+use crate::text::wrap;
+use crate::theme::Theme;
+use crate::unified_color::UnifiedColor;
+
+/// What went wrong, decides the title and the hint shown to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NoWifi,
+    /// No connection to the server, or no answer in time.
+    ServerUnreachable,
+    /// The server answered, but with an error status, if there was one, or
+    /// with broken redirects.
+    ServerError(Option<u16>),
+    BadPayload,
+    NotConfigured,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            ErrorKind::NoWifi => "No Wi-Fi",
+            ErrorKind::ServerUnreachable => "Server unreachable",
+            ErrorKind::ServerError(_) => "Server error",
+            ErrorKind::BadPayload => "Bad schedule data",
+            ErrorKind::NotConfigured => "Not configured",
+            ErrorKind::Internal => "Device error",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            ErrorKind::NoWifi => "None of the configured networks could be joined.",
+            ErrorKind::ServerUnreachable => "The schedule server did not answer.",
+            ErrorKind::ServerError(_) => "The schedule server refused to send the schedule.",
+            ErrorKind::BadPayload => "The server sent a schedule this display cannot read.",
+            ErrorKind::NotConfigured => "Set the server URL and Wi-Fi credentials for:",
+            ErrorKind::Internal => "Something unexpected failed on the display.",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::ServerError(Some(status)) => write!(f, "{} {status}", self.title()),
+            _ => f.write_str(self.title()),
+        }
+    }
+}
+
+/// Full-screen error: a chromatic title bar, a hint, the technical detail
+/// and the device ID so the display can be found and fixed.
+#[derive(Clone)]
+pub struct ErrorScreen<'a, F, C> {
+    top_left: Point,
+    size: Size,
+    kind: ErrorKind,
+    detail: &'a str,
+    device_id: &'a str,
+    theme: Theme,
+
+    color_converter: F,
+    _phantom_color: PhantomData<C>,
+}
+
+impl<'a, F, C> ErrorScreen<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+{
+    pub fn new(
+        top_left: Point,
+        size: Size,
+        kind: ErrorKind,
+        detail: &'a str,
+        device_id: &'a str,
+        color_converter: F,
+    ) -> Self {
+        ErrorScreen {
+            top_left,
+            size,
+            kind,
+            detail,
+            device_id,
+            theme: Theme::default(),
+            color_converter,
+            _phantom_color: PhantomData,
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let theme = &self.theme;
+        let font_width = theme.font_width();
+        let font_height = theme.font_height();
+        let small_font_width = theme.small_font_width();
+        let small_font_height = theme.small_font_height();
+        let padding = theme.padding;
+        let width = self.size.width as i32;
+        let center_x = self.top_left.x + width / 2;
+        let text_width = width - 2 * padding;
+
+        Rectangle::new(self.top_left, self.size)
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(UnifiedColor::White.into_with(self.color_converter))
+                    .build(),
+            )
+            .draw(display)?;
+
+        // Title bar
+        let bar_height = font_height + 2 * padding;
+        Rectangle::new(self.top_left, Size::new(self.size.width, bar_height as u32))
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(
+                        Theme::accent(theme.accents.headings).into_with(self.color_converter),
+                    )
+                    .build(),
+            )
+            .draw(display)?;
+
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build();
+
+        let title_style: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.font)
+            .text_color(UnifiedColor::White.into_with(self.color_converter))
+            .build();
+        Text::with_text_style(
+            &self.kind.to_string(),
+            Point::new(center_x, self.top_left.y + padding),
+            title_style,
+            centered,
+        )
+        .draw(display)?;
+
+        // Hint
+        let text_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.font)
+            .text_color(UnifiedColor::Black.into_with(self.color_converter))
+            .build();
+        let mut y = self.top_left.y + bar_height + 2 * padding;
+        for line in wrap(self.kind.hint(), (text_width / font_width).max(1) as usize) {
+            Text::with_text_style(&line, Point::new(center_x, y), text_style_black, centered)
+                .draw(display)?;
+            y += font_height;
+        }
+
+        // Without configuration the device ID is what the user needs most.
+        if self.kind == ErrorKind::NotConfigured {
+            y += padding;
+            let id_style: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+                .font(theme.font)
+                .text_color(Theme::accent(theme.accents.headings).into_with(self.color_converter))
+                .build();
+            Text::with_text_style(self.device_id, Point::new(center_x, y), id_style, centered)
+                .draw(display)?;
+            y += font_height;
+        }
+
+        // Detail, cut off above the footer
+        let text_small_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.small_font)
+            .text_color(UnifiedColor::Black.into_with(self.color_converter))
+            .build();
+        let footer_y = self.top_left.y + self.size.height as i32 - padding - small_font_height;
+        y += 2 * padding;
+        for line in wrap(self.detail, (text_width / small_font_width).max(1) as usize) {
+            if y + small_font_height > footer_y - padding {
+                break;
+            }
+            Text::with_text_style(
+                &line,
+                Point::new(center_x, y),
+                text_small_style_black,
+                centered,
+            )
+            .draw(display)?;
+            y += small_font_height;
+        }
+
+        // Footer
+        let footer = format!("Device {} - retrying on next wake", self.device_id);
+        Text::with_text_style(
+            &footer,
+            Point::new(center_x, footer_y),
+            text_small_style_black,
+            centered,
+        )
+        .draw(display)?;
+
+        Ok(())
+    }
+}
+
+impl<'a, F, C, D> Component<D> for ErrorScreen<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        ErrorScreen {
+            top_left: area.top_left,
+            size: area.size,
+            ..self.clone()
+        }
+        .draw(display)
+    }
+}
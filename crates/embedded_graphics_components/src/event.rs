@@ -0,0 +1,328 @@
+use std::fmt;
+
+/// One meeting, shared by all schedule components. Times are hours of the
+/// day (`13.5` is 13:30), dates are the strings shown in headers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event<'a> {
+    pub date: &'a str,
+    pub start: f32,
+    pub end: f32,
+    pub title: &'a str,
+    pub organizer: Option<&'a str>,
+    /// Room or person the event is booked for, on multi-room boards.
+    pub resource: Option<&'a str>,
+}
+
+impl<'a> Event<'a> {
+    pub const fn new(date: &'a str, start: f32, end: f32, title: &'a str) -> Self {
+        Event {
+            date,
+            start,
+            end,
+            title,
+            organizer: None,
+            resource: None,
+        }
+    }
+
+    pub const fn with_organizer(mut self, organizer: &'a str) -> Self {
+        self.organizer = Some(organizer);
+        self
+    }
+
+    pub const fn with_resource(mut self, resource: &'a str) -> Self {
+        self.resource = Some(resource);
+        self
+    }
+
+    /// Whether the event is running at `time` on `date`.
+    pub fn is_running(&self, date: &str, time: f32) -> bool {
+        self.date == date && self.start <= time && time < self.end
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.end - self.start
+    }
+
+    /// Whether both take up some of the same time. Events without a
+    /// duration still clash with whatever runs or starts at their time.
+    pub fn overlaps(&self, other: &Event<'_>) -> bool {
+        (self.start < other.end && other.start < self.end) || self.start == other.start
+    }
+
+    /// The event with times safe to draw: within the day and `start <= end`.
+    /// `None` if a time is not a number.
+    pub fn normalized(&self) -> Option<Event<'a>> {
+        if !self.start.is_finite() || !self.end.is_finite() {
+            return None;
+        }
+        let (start, end) = if self.end < self.start {
+            (self.end, self.start)
+        } else {
+            (self.start, self.end)
+        };
+        Some(Event {
+            start: start.clamp(0.0, 24.0),
+            end: end.clamp(0.0, 24.0),
+            ..*self
+        })
+    }
+}
+
+/// Something wrong with an event's times, see [`validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventIssue {
+    /// A time is NaN or infinite, the event is left out.
+    NotANumber,
+    /// Ends before it starts, drawn with the times swapped.
+    Reversed,
+    /// Starts when it ends, drawn as a thin marker.
+    Empty,
+    /// Reaches outside 0:00-24:00, cut to the day.
+    OutsideDay,
+}
+
+impl EventIssue {
+    /// Whether the event is left out instead of drawn corrected.
+    pub fn is_rejected(self) -> bool {
+        self == EventIssue::NotANumber
+    }
+}
+
+impl fmt::Display for EventIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EventIssue::NotANumber => "time is not a number",
+            EventIssue::Reversed => "ends before it starts",
+            EventIssue::Empty => "has no duration",
+            EventIssue::OutsideDay => "reaches outside the day",
+        })
+    }
+}
+
+/// Issues of all `events`, as `(index, issue)`. Events can have several.
+pub fn validate(events: &[Event<'_>]) -> Vec<(usize, EventIssue)> {
+    let mut issues = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        if !event.start.is_finite() || !event.end.is_finite() {
+            issues.push((i, EventIssue::NotANumber));
+            continue;
+        }
+        if event.end < event.start {
+            issues.push((i, EventIssue::Reversed));
+        }
+        if event.end == event.start {
+            issues.push((i, EventIssue::Empty));
+        }
+        let day = 0.0..=24.0;
+        if !day.contains(&event.start) || !day.contains(&event.end) {
+            issues.push((i, EventIssue::OutsideDay));
+        }
+    }
+    issues
+}
+
+/// Places overlapping `events` side by side: `(lane, lanes)` per event, in
+/// the order given. Events that overlap get different lanes, and every event
+/// of a group of overlaps gets the group's lane count so their boxes line up.
+/// Expects [normalized](Event::normalized) events.
+pub fn lanes(events: &[Event<'_>]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_by(|&a, &b| {
+        (events[a].start.total_cmp(&events[b].start))
+            .then_with(|| events[a].end.total_cmp(&events[b].end))
+    });
+
+    let mut lane_of = vec![0; events.len()];
+    let mut group_of = vec![0; events.len()];
+    // Lane count of each group of overlapping events
+    let mut group_lanes: Vec<usize> = Vec::new();
+    let mut group: Vec<usize> = Vec::new();
+    // Last event of each lane of the current group
+    let mut lane_ends: Vec<&Event<'_>> = Vec::new();
+    for i in order {
+        let event = &events[i];
+        if !group.iter().any(|&j| events[j].overlaps(event)) {
+            group.clear();
+            lane_ends.clear();
+            group_lanes.push(0);
+        }
+        lane_of[i] = match lane_ends.iter().position(|last| !last.overlaps(event)) {
+            Some(lane) => {
+                lane_ends[lane] = event;
+                lane
+            }
+            None => {
+                lane_ends.push(event);
+                lane_ends.len() - 1
+            }
+        };
+        group_of[i] = group_lanes.len() - 1;
+        *group_lanes.last_mut().unwrap() = lane_ends.len();
+        group.push(i);
+    }
+
+    (0..events.len())
+        .map(|i| (lane_of[i], group_lanes[group_of[i]]))
+        .collect()
+}
+
+/// `13.5` -> `"13:30"`.
+pub fn format_time(hours: f32) -> String {
+    let minutes = (hours * 60.0).round() as i32;
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// `"dd.mm.yyyy"` -> `(year, month, day)`, for ordering dates.
+pub fn parse_date(date: &str) -> Option<(u16, u8, u8)> {
+    let mut parts = date.trim().split('.');
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let year = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+/// Events sorted by date, then start time. Unparsable dates go last, in
+/// string order.
+pub fn chronological<'e, 'a>(events: &'e [Event<'a>]) -> Vec<&'e Event<'a>> {
+    let mut sorted: Vec<_> = events.iter().collect();
+    sorted.sort_by(|a, b| {
+        let key = |event: &Event<'_>| (parse_date(event.date).is_none(), parse_date(event.date));
+        key(a)
+            .cmp(&key(b))
+            .then_with(|| a.date.cmp(b.date))
+            .then_with(|| a.start.total_cmp(&b.start))
+    });
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Hours as they come from the network: mostly sensible, sometimes
+    /// outside the day or not a number at all.
+    fn time() -> impl Strategy<Value = f32> {
+        prop_oneof![
+            8 => (0u8..=96).prop_map(|quarters| quarters as f32 / 4.0),
+            4 => -48.0f32..72.0,
+            1 => Just(f32::NAN),
+            1 => Just(f32::INFINITY),
+            1 => Just(f32::NEG_INFINITY),
+        ]
+    }
+
+    /// Overlapping, empty and reversed lengths included.
+    fn length() -> impl Strategy<Value = f32> {
+        prop_oneof![
+            2 => Just(0.0f32),
+            6 => (1u8..=16).prop_map(|quarters| quarters as f32 / 4.0),
+            2 => -6.0f32..6.0,
+        ]
+    }
+
+    fn date() -> impl Strategy<Value = String> {
+        prop_oneof![
+            4 => prop::sample::select(vec!["01.02.2024", "02.02.2024", "29.02.2024"])
+                .prop_map(String::from),
+            1 => prop::sample::select(vec!["", "31.02.2024", "2024-02-01", "soon"])
+                .prop_map(String::from),
+            1 => "[0-9.]{0,12}",
+        ]
+    }
+
+    fn raw_events() -> impl Strategy<Value = Vec<(String, f32, f32)>> {
+        prop::collection::vec(
+            (date(), time(), length())
+                .prop_map(|(date, start, length)| (date, start, start + length)),
+            0..24,
+        )
+    }
+
+    fn events(raw: &[(String, f32, f32)]) -> Vec<Event<'_>> {
+        raw.iter()
+            .map(|(date, start, end)| Event::new(date, *start, *end, "Meeting"))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn normalized_events_fit_the_day(raw in raw_events()) {
+            let events = events(&raw);
+            let issues = validate(&events);
+            for (i, event) in events.iter().enumerate() {
+                let rejected = issues.iter().any(|&(j, issue)| j == i && issue.is_rejected());
+                match event.normalized() {
+                    None => prop_assert!(rejected),
+                    Some(normalized) => {
+                        prop_assert!(!rejected);
+                        prop_assert!(0.0 <= normalized.start);
+                        prop_assert!(normalized.start <= normalized.end);
+                        prop_assert!(normalized.end <= 24.0);
+                        prop_assert_eq!(normalized.date, event.date);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn chronological_orders_every_event(raw in raw_events()) {
+            let events: Vec<Event<'_>> =
+                events(&raw).iter().filter_map(Event::normalized).collect();
+            let sorted = chronological(&events);
+            prop_assert_eq!(sorted.len(), events.len());
+            for pair in sorted.windows(2) {
+                let (a, b) = (parse_date(pair[0].date), parse_date(pair[1].date));
+                match (a, b) {
+                    (Some(a), Some(b)) => prop_assert!(a <= b),
+                    (None, Some(_)) => prop_assert!(false, "unparsable date before a valid one"),
+                    _ => {}
+                }
+                if pair[0].date == pair[1].date {
+                    prop_assert!(pair[0].start <= pair[1].start);
+                }
+            }
+        }
+
+        #[test]
+        fn lanes_keep_overlaps_apart(raw in raw_events()) {
+            let events: Vec<Event<'_>> =
+                events(&raw).iter().filter_map(Event::normalized).collect();
+            // One column per date, as the schedule table lays them out
+            let mut dates: Vec<&str> = events.iter().map(|event| event.date).collect();
+            dates.sort_unstable();
+            dates.dedup();
+            for date in dates {
+                let column: Vec<Event<'_>> =
+                    events.iter().filter(|event| event.date == date).copied().collect();
+                let placed = lanes(&column);
+                prop_assert_eq!(placed.len(), column.len());
+                for (i, a) in column.iter().enumerate() {
+                    prop_assert!(placed[i].0 < placed[i].1);
+                    for (j, b) in column.iter().enumerate().skip(i + 1) {
+                        if a.overlaps(b) {
+                            prop_assert_ne!(placed[i].0, placed[j].0, "{:?} and {:?}", a, b);
+                            prop_assert_eq!(placed[i].1, placed[j].1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lanes_share_groups_only() {
+        let events = [
+            Event::new("d", 9.0, 11.0, "a"),
+            Event::new("d", 10.0, 12.0, "b"),
+            Event::new("d", 11.0, 13.0, "c"),
+            Event::new("d", 14.0, 15.0, "d"),
+            Event::new("d", 14.0, 14.0, "e"),
+        ];
+        assert_eq!(lanes(&events), [(0, 2), (1, 2), (0, 2), (1, 2), (0, 2)]);
+    }
+}
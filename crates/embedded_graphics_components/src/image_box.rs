@@ -0,0 +1,450 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use embedded_graphics::image::GetPixel;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyleBuilder, Rectangle};
+use serde::Deserialize;
+use tinybmp::{Bmp, ParseError};
+
+use crate::component::Component;
+use crate::unified_color::UnifiedColor;
+
+/// How an image is sized to its box. Whatever the box doesn't cover stays
+/// white.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+    /// Scaled to fit the box whole, keeping its aspect ratio.
+    #[default]
+    Fit,
+    /// Scaled to cover the box, keeping its aspect ratio, edges cut off.
+    Fill,
+    /// Unscaled in the middle of the box, cut off if larger.
+    Center,
+}
+
+/// Bytes that aren't a BMP image tinybmp can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageError(pub ParseError);
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ParseError::UnsupportedBpp(bpp) => write!(f, "{bpp} bits per pixel aren't supported"),
+            ParseError::UnexpectedEndOfFile => write!(f, "image is truncated"),
+            ParseError::InvalidFileSignature(_) => write!(f, "not a BMP image"),
+            ParseError::UnsupportedCompressionMethod(method) => {
+                write!(f, "compression method {method} isn't supported")
+            }
+            ParseError::UnsupportedHeaderLength(len) => {
+                write!(f, "header of {len} bytes isn't supported")
+            }
+            ParseError::UnsupportedChannelMasks => write!(f, "channel masks aren't supported"),
+            ParseError::InvalidImageDimensions => write!(f, "invalid image dimensions"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// A BMP image, e.g. a logo, scaled into its box and reduced to the panel's
+/// colors. The bytes can come from `include_bytes!` or be loaded at runtime.
+#[derive(Clone)]
+pub struct ImageBox<'a, F, C> {
+    top_left: Point,
+    size: Size,
+    bmp: Bmp<'a, Rgb888>,
+    fit: ImageFit,
+    chromatic: Option<Rgb888>,
+    color_converter: F,
+    _phantom_color: PhantomData<C>,
+}
+
+impl<'a, F, C> ImageBox<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+{
+    pub fn new(
+        top_left: Point,
+        size: Size,
+        bmp: &'a [u8],
+        color_converter: F,
+    ) -> Result<Self, ImageError> {
+        Ok(ImageBox {
+            top_left,
+            size,
+            bmp: Bmp::from_slice(bmp).map_err(ImageError)?,
+            fit: ImageFit::default(),
+            chromatic: Some(Rgb888::RED),
+            color_converter,
+            _phantom_color: PhantomData,
+        })
+    }
+
+    pub fn with_fit(mut self, fit: ImageFit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// What the panel's third color looks like, red by default. Image colors
+    /// closer to it than to black or white are drawn chromatic; `None` keeps
+    /// the image black and white.
+    pub fn with_chromatic(mut self, chromatic: Option<Rgb888>) -> Self {
+        self.chromatic = chromatic;
+        self
+    }
+
+    /// Size of the image itself, unscaled.
+    pub fn image_size(&self) -> Size {
+        self.bmp.size()
+    }
+
+    /// Where the scaled image lands, partly outside the box with
+    /// [`ImageFit::Fill`] and [`ImageFit::Center`].
+    pub fn image_area(&self) -> Rectangle {
+        let image = self.image_size();
+        let scaled = match self.fit {
+            ImageFit::Fit => fitted(image, self.size, false),
+            ImageFit::Fill => fitted(image, self.size, true),
+            ImageFit::Center => image,
+        };
+        let offset = Point::new(
+            self.size.width as i32 - scaled.width as i32,
+            self.size.height as i32 - scaled.height as i32,
+        ) / 2;
+        Rectangle::new(self.top_left + offset, scaled)
+    }
+
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let area = Rectangle::new(self.top_left, self.size);
+        area.into_styled(
+            PrimitiveStyleBuilder::new()
+                .fill_color(UnifiedColor::White.into_with(self.color_converter))
+                .build(),
+        )
+        .draw(display)?;
+
+        let image = self.image_size();
+        let scaled = self.image_area();
+        let visible = scaled.intersection(&area);
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+
+        // Nearest neighbor, each drawn pixel picks the image pixel it covers
+        let source = |p: Point| {
+            let p = p - scaled.top_left;
+            Point::new(
+                (u64::from(p.x as u32) * u64::from(image.width) / u64::from(scaled.size.width))
+                    as i32,
+                (u64::from(p.y as u32) * u64::from(image.height) / u64::from(scaled.size.height))
+                    as i32,
+            )
+        };
+        display.fill_contiguous(
+            &visible,
+            visible.points().map(|p| {
+                let color = self.bmp.pixel(source(p)).unwrap_or(Rgb888::WHITE);
+                quantize(color, self.chromatic).into_with(self.color_converter)
+            }),
+        )
+    }
+}
+
+/// `image` scaled to just fit `into`, or with `cover` to just cover it.
+fn fitted(image: Size, into: Size, cover: bool) -> Size {
+    if image.width == 0 || image.height == 0 {
+        return Size::zero();
+    }
+    // Compare the aspect ratios without rounding
+    let wider = u64::from(image.width) * u64::from(into.height)
+        > u64::from(into.width) * u64::from(image.height);
+    if wider != cover {
+        let height = u64::from(image.height) * u64::from(into.width) / u64::from(image.width);
+        Size::new(into.width, (height as u32).max(1))
+    } else {
+        let width = u64::from(image.width) * u64::from(into.height) / u64::from(image.height);
+        Size::new((width as u32).max(1), into.height)
+    }
+}
+
+/// The panel color closest to `color`.
+fn quantize(color: Rgb888, chromatic: Option<Rgb888>) -> UnifiedColor {
+    let distance = |to: Rgb888| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(color.r(), to.r()) + d(color.g(), to.g()) + d(color.b(), to.b())
+    };
+    [
+        (UnifiedColor::Black, Rgb888::BLACK),
+        (UnifiedColor::White, Rgb888::WHITE),
+    ]
+    .into_iter()
+    .chain(chromatic.map(|chromatic| (UnifiedColor::Chromatic, chromatic)))
+    .min_by_key(|&(_, to)| distance(to))
+    .map_or(UnifiedColor::White, |(nearest, _)| nearest)
+}
+
+impl<F, C, D> Component<D> for ImageBox<'_, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    /// The image at its own size, scaled down if it doesn't fit.
+    fn measure(&self, available: Size) -> Size {
+        let image = self.image_size();
+        if image.width <= available.width && image.height <= available.height {
+            image
+        } else {
+            fitted(image, available, false)
+        }
+    }
+
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        ImageBox {
+            top_left: area.top_left,
+            size: area.size,
+            ..self.clone()
+        }
+        .draw(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    /// 24 bit BMP of `size`, colored by `pixel`.
+    fn bmp(size: Size, pixel: impl Fn(Point) -> Rgb888) -> Vec<u8> {
+        let stride = (size.width * 3).next_multiple_of(4);
+        let data_len = stride * size.height;
+        let mut bytes = Vec::new();
+        bytes.extend(b"BM");
+        for value in [54 + data_len, 0, 54, 40] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(size.width.to_le_bytes());
+        bytes.extend(size.height.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(24u16.to_le_bytes());
+        for value in [0, data_len, 2835, 2835, 0, 0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        // Bottom row first
+        for y in (0..size.height).rev() {
+            let row = bytes.len();
+            for x in 0..size.width {
+                let color = pixel(Point::new(x as i32, y as i32));
+                bytes.extend([color.b(), color.g(), color.r()]);
+            }
+            bytes.resize(row + stride as usize, 0);
+        }
+        bytes
+    }
+
+    fn rgb(color: UnifiedColor) -> Rgb888 {
+        match color {
+            UnifiedColor::White => Rgb888::WHITE,
+            UnifiedColor::Black => Rgb888::BLACK,
+            UnifiedColor::Chromatic => Rgb888::RED,
+        }
+    }
+
+    type RgbBox<'a> = ImageBox<'a, fn(UnifiedColor) -> Rgb888, Rgb888>;
+
+    fn image_box(bmp: &[u8], top_left: Point, size: Size, fit: ImageFit) -> RgbBox<'_> {
+        ImageBox::new(top_left, size, bmp, rgb as fn(_) -> _)
+            .unwrap()
+            .with_fit(fit)
+    }
+
+    /// Frame buffer that remembers which pixels were never drawn.
+    struct Frame {
+        size: Size,
+        pixels: Vec<Option<Rgb888>>,
+    }
+
+    impl Frame {
+        fn new(size: Size) -> Self {
+            Frame {
+                size,
+                pixels: vec![None; (size.width * size.height) as usize],
+            }
+        }
+
+        fn at(&self, point: Point) -> Option<Rgb888> {
+            self.pixels[(point.y as u32 * self.size.width + point.x as u32) as usize]
+        }
+    }
+
+    impl DrawTarget for Frame {
+        type Color = Rgb888;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                assert!(self.bounding_box().contains(point), "drawn at {point:?}");
+                let index = (point.y as u32 * self.size.width + point.x as u32) as usize;
+                self.pixels[index] = Some(color);
+            }
+            Ok(())
+        }
+    }
+
+    impl OriginDimensions for Frame {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    const WIDE: Size = Size::new(40, 20);
+    const TALL: Size = Size::new(20, 40);
+
+    fn black(_: Point) -> Rgb888 {
+        Rgb888::BLACK
+    }
+
+    #[test]
+    fn fits_to_the_aspect_ratio() {
+        assert_eq!(fitted(WIDE, Size::new(30, 30), false), Size::new(30, 15));
+        assert_eq!(fitted(TALL, Size::new(30, 30), false), Size::new(15, 30));
+        assert_eq!(fitted(WIDE, Size::new(30, 30), true), Size::new(60, 30));
+        assert_eq!(fitted(TALL, Size::new(30, 30), true), Size::new(30, 60));
+        // Never thinner than a pixel
+        assert_eq!(
+            fitted(Size::new(100, 1), Size::new(10, 10), false),
+            Size::new(10, 1)
+        );
+        assert_eq!(fitted(Size::zero(), Size::new(10, 10), false), Size::zero());
+    }
+
+    #[test]
+    fn places_the_image_in_the_box() {
+        let top_left = Point::new(5, 5);
+        let size = Size::new(30, 30);
+        let wide = bmp(WIDE, black);
+        let tall = bmp(TALL, black);
+        let area = |bmp, fit| image_box(bmp, top_left, size, fit).image_area();
+
+        assert_eq!(
+            area(&wide, ImageFit::Fit),
+            Rectangle::new(Point::new(5, 12), Size::new(30, 15))
+        );
+        assert_eq!(
+            area(&tall, ImageFit::Fit),
+            Rectangle::new(Point::new(12, 5), Size::new(15, 30))
+        );
+        assert_eq!(
+            area(&wide, ImageFit::Fill),
+            Rectangle::new(Point::new(-10, 5), Size::new(60, 30))
+        );
+        assert_eq!(
+            area(&tall, ImageFit::Fill),
+            Rectangle::new(Point::new(5, -10), Size::new(30, 60))
+        );
+        assert_eq!(
+            area(&wide, ImageFit::Center),
+            Rectangle::new(Point::new(0, 10), WIDE)
+        );
+        assert_eq!(
+            area(&tall, ImageFit::Center),
+            Rectangle::new(Point::new(10, 0), TALL)
+        );
+    }
+
+    #[test]
+    fn draws_only_inside_the_box() {
+        let frame = Size::new(60, 60);
+        let area = Rectangle::new(Point::new(15, 15), Size::new(30, 30));
+        for image in [WIDE, TALL] {
+            let bmp = bmp(image, black);
+            for fit in [ImageFit::Fit, ImageFit::Fill, ImageFit::Center] {
+                let image_box = image_box(&bmp, area.top_left, area.size, fit);
+                let mut display = Frame::new(frame);
+                image_box.draw(&mut display).unwrap();
+
+                let image_area = image_box.image_area();
+                for point in Rectangle::new(Point::zero(), frame).points() {
+                    let expected = if !area.contains(point) {
+                        None
+                    } else if image_area.contains(point) {
+                        Some(Rgb888::BLACK)
+                    } else {
+                        Some(Rgb888::WHITE)
+                    };
+                    assert_eq!(display.at(point), expected, "{image:?} {fit:?} {point:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn draws_nothing_into_an_empty_box() {
+        let bmp = bmp(WIDE, black);
+        for fit in [ImageFit::Fit, ImageFit::Fill, ImageFit::Center] {
+            let image_box = image_box(&bmp, Point::new(5, 5), Size::zero(), fit);
+            if fit != ImageFit::Center {
+                assert!(image_box.image_area().is_zero_sized(), "{fit:?}");
+            }
+            let mut display = Frame::new(Size::new(60, 60));
+            image_box.draw(&mut display).unwrap();
+            assert!(display.pixels.iter().all(Option::is_none), "{fit:?}");
+        }
+    }
+
+    #[test]
+    fn reduces_to_panel_colors() {
+        assert_eq!(
+            quantize(Rgb888::BLACK, Some(Rgb888::RED)),
+            UnifiedColor::Black
+        );
+        assert_eq!(
+            quantize(Rgb888::new(200, 200, 200), Some(Rgb888::RED)),
+            UnifiedColor::White
+        );
+        assert_eq!(
+            quantize(Rgb888::new(220, 30, 20), Some(Rgb888::RED)),
+            UnifiedColor::Chromatic
+        );
+        assert_eq!(quantize(Rgb888::RED, None), UnifiedColor::Black);
+        assert_eq!(
+            quantize(Rgb888::new(255, 180, 180), None),
+            UnifiedColor::White
+        );
+    }
+
+    #[test]
+    fn draws_red_chromatic_unless_disabled() {
+        let size = Size::new(2, 1);
+        let bmp = bmp(size, |point| {
+            if point.x == 0 {
+                Rgb888::RED
+            } else {
+                Rgb888::WHITE
+            }
+        });
+        let drawn = |chromatic| {
+            let mut display = Frame::new(size);
+            image_box(&bmp, Point::zero(), size, ImageFit::Fit)
+                .with_chromatic(chromatic)
+                .draw(&mut display)
+                .unwrap();
+            display.pixels
+        };
+        assert_eq!(
+            drawn(Some(Rgb888::RED)),
+            [Some(Rgb888::RED), Some(Rgb888::WHITE)]
+        );
+        assert_eq!(drawn(None), [Some(Rgb888::BLACK), Some(Rgb888::WHITE)]);
+    }
+}
@@ -0,0 +1,312 @@
+use std::num::NonZeroU64;
+
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use serde::Deserialize;
+
+use crate::component::Component;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Top to bottom.
+    Vertical,
+    /// Left to right.
+    Horizontal,
+}
+
+impl Direction {
+    fn along(self, size: Size) -> u32 {
+        match self {
+            Direction::Vertical => size.height,
+            Direction::Horizontal => size.width,
+        }
+    }
+
+    fn across(self, size: Size) -> u32 {
+        match self {
+            Direction::Vertical => size.width,
+            Direction::Horizontal => size.height,
+        }
+    }
+
+    fn size(self, along: u32, across: u32) -> Size {
+        match self {
+            Direction::Vertical => Size::new(across, along),
+            Direction::Horizontal => Size::new(along, across),
+        }
+    }
+
+    fn offset(self, along: u32) -> Point {
+        match self {
+            Direction::Vertical => Point::new(0, along as i32),
+            Direction::Horizontal => Point::new(along as i32, 0),
+        }
+    }
+}
+
+/// Share of a stack a child gets along the stack's direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    /// Exactly this many pixels, as far as they fit.
+    Fixed(u32),
+    /// What the child measures.
+    Auto,
+    /// A share of what fixed and auto children leave, by weight.
+    Flex(u32),
+}
+
+/// `area` shrunk by `padding` on every side.
+pub fn inset(area: Rectangle, padding: u32) -> Rectangle {
+    let size = Size::new(
+        area.size.width.saturating_sub(2 * padding),
+        area.size.height.saturating_sub(2 * padding),
+    );
+    Rectangle::new(area.top_left + Point::new_equal(padding as i32), size)
+}
+
+/// Splits `area` along `direction` into one rectangle per length, `spacing`
+/// apart. [`Length::Auto`] is resolved by `measure(index, available)`.
+pub fn split_with(
+    area: Rectangle,
+    direction: Direction,
+    spacing: u32,
+    lengths: &[Length],
+    mut measure: impl FnMut(usize, Size) -> Size,
+) -> Vec<Rectangle> {
+    let across = direction.across(area.size);
+    let along = direction.along(area.size);
+    // Gaps never take more than the area, however large the spacing
+    let count = u32::try_from(lengths.len()).unwrap_or(u32::MAX);
+    let gaps = spacing.saturating_mul(count.saturating_sub(1)).min(along);
+    let mut left = along - gaps;
+
+    // Fixed children first, then auto ones get what they measure out of
+    // the rest, flex ones share what is left
+    let mut sizes = vec![0; lengths.len()];
+    for (i, length) in lengths.iter().enumerate() {
+        if let Length::Fixed(size) = *length {
+            sizes[i] = size.min(left);
+            left -= sizes[i];
+        }
+    }
+    for (i, length) in lengths.iter().enumerate() {
+        if *length == Length::Auto {
+            sizes[i] = direction
+                .along(measure(i, direction.size(left, across)))
+                .min(left);
+            left -= sizes[i];
+        }
+    }
+
+    let weights: u64 = lengths
+        .iter()
+        .map(|length| match length {
+            Length::Flex(weight) => u64::from(*weight),
+            _ => 0,
+        })
+        .sum();
+    if let Some(weights) = NonZeroU64::new(weights) {
+        let mut rest = left;
+        let last_flex = lengths
+            .iter()
+            .rposition(|length| matches!(length, Length::Flex(_)));
+        for (i, length) in lengths.iter().enumerate() {
+            if let Length::Flex(weight) = *length {
+                // The last flex child takes what rounding left over
+                sizes[i] = if Some(i) == last_flex {
+                    rest
+                } else {
+                    (u64::from(left) * u64::from(weight) / weights) as u32
+                };
+                rest -= sizes[i];
+            }
+        }
+    }
+
+    let mut offset = 0;
+    sizes
+        .into_iter()
+        .map(|size| {
+            let rect = Rectangle::new(
+                area.top_left + direction.offset(offset),
+                direction.size(size, across),
+            );
+            offset = offset
+                .saturating_add(size)
+                .saturating_add(spacing)
+                .min(along);
+            rect
+        })
+        .collect()
+}
+
+/// [`split_with`] for lengths without [`Length::Auto`], which get nothing.
+pub fn split(
+    area: Rectangle,
+    direction: Direction,
+    spacing: u32,
+    lengths: &[Length],
+) -> Vec<Rectangle> {
+    split_with(area, direction, spacing, lengths, |_, _| Size::zero())
+}
+
+/// Children one after another, vertically or horizontally.
+pub struct Stack<'a, D> {
+    direction: Direction,
+    spacing: u32,
+    padding: u32,
+    children: Vec<(Length, Box<dyn Component<D> + 'a>)>,
+}
+
+impl<'a, D: DrawTarget> Stack<'a, D> {
+    pub fn new(direction: Direction) -> Self {
+        Stack {
+            direction,
+            spacing: 0,
+            padding: 0,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn vertical() -> Self {
+        Stack::new(Direction::Vertical)
+    }
+
+    pub fn horizontal() -> Self {
+        Stack::new(Direction::Horizontal)
+    }
+
+    /// Gap between children.
+    pub fn spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Gap around all children.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn child(mut self, length: Length, child: impl Component<D> + 'a) -> Self {
+        self.children.push((length, Box::new(child)));
+        self
+    }
+
+    /// Where each child goes when the stack fills `area`.
+    pub fn areas(&self, area: Rectangle) -> Vec<Rectangle> {
+        let lengths: Vec<Length> = self.children.iter().map(|(length, _)| *length).collect();
+        split_with(
+            inset(area, self.padding),
+            self.direction,
+            self.spacing,
+            &lengths,
+            |i, available| self.children[i].1.measure(available),
+        )
+    }
+}
+
+impl<D: DrawTarget> Component<D> for Stack<'_, D> {
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        for ((_, child), area) in self.children.iter().zip(self.areas(area)) {
+            child.draw_in(display, area)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area() -> Rectangle {
+        Rectangle::new(Point::new(10, 20), Size::new(100, 200))
+    }
+
+    fn lengths_along(rects: &[Rectangle]) -> Vec<u32> {
+        rects.iter().map(|rect| rect.size.height).collect()
+    }
+
+    #[test]
+    fn fixed_children_are_reserved_before_auto_ones() {
+        // An auto child that wants everything it is offered
+        let greedy = |_, available: Size| available;
+        let rects = split_with(
+            area(),
+            Direction::Vertical,
+            0,
+            &[Length::Auto, Length::Fixed(40)],
+            greedy,
+        );
+        assert_eq!(lengths_along(&rects), [160, 40]);
+        assert_eq!(rects[1].top_left, Point::new(10, 180));
+    }
+
+    #[test]
+    fn auto_children_get_their_measure_before_flex_ones() {
+        let rects = split_with(
+            area(),
+            Direction::Vertical,
+            10,
+            &[
+                Length::Flex(1),
+                Length::Auto,
+                Length::Fixed(30),
+                Length::Flex(2),
+            ],
+            |_, _| Size::new(100, 50),
+        );
+        // 200 - 3 gaps - 30 fixed - 50 auto = 90 shared 1:2
+        assert_eq!(lengths_along(&rects), [30, 50, 30, 60]);
+        let tops: Vec<i32> = rects.iter().map(|rect| rect.top_left.y).collect();
+        assert_eq!(tops, [20, 60, 120, 160]);
+    }
+
+    #[test]
+    fn clamps_what_doesnt_fit() {
+        let rects = split_with(
+            area(),
+            Direction::Horizontal,
+            0,
+            &[
+                Length::Auto,
+                Length::Fixed(80),
+                Length::Fixed(80),
+                Length::Flex(1),
+            ],
+            |_, available| available,
+        );
+        let widths: Vec<u32> = rects.iter().map(|rect| rect.size.width).collect();
+        assert_eq!(widths, [0, 80, 20, 0]);
+        assert!(rects.iter().all(|rect| rect.size.height == 200));
+    }
+
+    #[test]
+    fn spacing_larger_than_the_area_leaves_nothing() {
+        for (spacing, second) in [(150, 170), (u32::MAX, 220)] {
+            let rects = split_with(
+                area(),
+                Direction::Vertical,
+                spacing,
+                &[Length::Fixed(10), Length::Flex(u32::MAX), Length::Auto],
+                |_, available| available,
+            );
+            assert_eq!(lengths_along(&rects), [0, 0, 0]);
+            // Pushed to the end of the area at most
+            let tops: Vec<i32> = rects.iter().map(|rect| rect.top_left.y).collect();
+            assert_eq!(tops, [20, second, 220]);
+        }
+    }
+
+    #[test]
+    fn large_weights_share_without_overflow() {
+        let rects = split(
+            area(),
+            Direction::Vertical,
+            0,
+            &[Length::Flex(u32::MAX), Length::Flex(u32::MAX)],
+        );
+        assert_eq!(lengths_along(&rects), [100, 100]);
+    }
+}
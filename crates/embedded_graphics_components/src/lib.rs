@@ -1,3 +1,17 @@
+pub mod agenda_list;
 pub mod battery_indicator;
+pub mod component;
+pub mod dirty;
+pub mod error_screen;
+pub mod event;
+pub mod image_box;
+pub mod layout;
+pub mod qr_code;
+pub mod room_status;
 pub mod schedule_table;
+pub mod screen;
+mod text;
+pub mod theme;
+pub mod time_grid;
 pub mod unified_color;
+pub mod week;
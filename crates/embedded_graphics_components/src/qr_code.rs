@@ -0,0 +1,325 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyleBuilder, Rectangle};
+use qrcodegen::{QrCode as Symbol, QrCodeEcc};
+use serde::Deserialize;
+
+use crate::component::Component;
+use crate::unified_color::UnifiedColor;
+
+/// Share of the code that can be damaged and still scan. Higher levels need
+/// more modules for the same data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCorrection {
+    /// About 7%.
+    Low,
+    /// About 15%.
+    #[default]
+    Medium,
+    /// About 25%.
+    Quartile,
+    /// About 30%.
+    High,
+}
+
+impl From<ErrorCorrection> for QrCodeEcc {
+    fn from(ecc: ErrorCorrection) -> Self {
+        match ecc {
+            ErrorCorrection::Low => QrCodeEcc::Low,
+            ErrorCorrection::Medium => QrCodeEcc::Medium,
+            ErrorCorrection::Quartile => QrCodeEcc::Quartile,
+            ErrorCorrection::High => QrCodeEcc::High,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrCodeError {
+    /// `len` bytes are more than the largest QR code holds at `ecc`.
+    TooLong { len: usize, ecc: ErrorCorrection },
+}
+
+impl fmt::Display for QrCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QrCodeError::TooLong { len, ecc } => {
+                write!(
+                    f,
+                    "{len} bytes don't fit a QR code with {ecc:?} error correction"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for QrCodeError {}
+
+/// A QR code, e.g. of a room's booking page. Modules are whole pixel
+/// squares, as large as the box allows, so the code stays sharp.
+#[derive(Clone)]
+pub struct QrCode<F, C> {
+    top_left: Point,
+    size: Size,
+    symbol: Symbol,
+    quiet_zone: u32,
+    color_converter: F,
+    _phantom_color: PhantomData<C>,
+}
+
+impl<F, C> QrCode<F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+{
+    pub fn new(
+        top_left: Point,
+        size: Size,
+        data: &str,
+        ecc: ErrorCorrection,
+        color_converter: F,
+    ) -> Result<Self, QrCodeError> {
+        let symbol = Symbol::encode_text(data, ecc.into()).map_err(|_| QrCodeError::TooLong {
+            len: data.len(),
+            ecc,
+        })?;
+        Ok(QrCode {
+            top_left,
+            size,
+            symbol,
+            quiet_zone: 4,
+            color_converter,
+            _phantom_color: PhantomData,
+        })
+    }
+
+    /// White margin around the code in modules, 4 by default as the standard
+    /// asks. Boxes too small for it draw the code without, the box's
+    /// surroundings must be light then.
+    pub fn with_quiet_zone(mut self, modules: u32) -> Self {
+        self.quiet_zone = modules;
+        self
+    }
+
+    /// Modules along each side of the code, without the quiet zone.
+    pub fn modules(&self) -> u32 {
+        self.symbol.size() as u32
+    }
+
+    /// Pixels along each side of a module, 0 when the box is smaller than
+    /// the code.
+    pub fn scale(&self) -> u32 {
+        self.fit(self.size).0
+    }
+
+    /// Where the code itself is drawn, centered in the box.
+    pub fn symbol_area(&self) -> Rectangle {
+        let side = self.scale() * self.modules();
+        let offset = Point::new(
+            self.size.width as i32 - side as i32,
+            self.size.height as i32 - side as i32,
+        ) / 2;
+        Rectangle::new(self.top_left + offset, Size::new_equal(side))
+    }
+
+    /// Module scale and quiet zone for a box of `size`.
+    fn fit(&self, size: Size) -> (u32, u32) {
+        let side = size.width.min(size.height);
+        let scale = side / (self.modules() + 2 * self.quiet_zone);
+        if scale > 0 {
+            (scale, self.quiet_zone)
+        } else {
+            (side / self.modules(), 0)
+        }
+    }
+
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        Rectangle::new(self.top_left, self.size)
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(UnifiedColor::White.into_with(self.color_converter))
+                    .build(),
+            )
+            .draw(display)?;
+
+        let scale = self.scale();
+        if scale == 0 {
+            return Ok(());
+        }
+        let area = self.symbol_area();
+        display.fill_contiguous(
+            &area,
+            area.points().map(|p| {
+                let module = (p - area.top_left) / scale as i32;
+                if self.symbol.get_module(module.x, module.y) {
+                    UnifiedColor::Black.into_with(self.color_converter)
+                } else {
+                    UnifiedColor::White.into_with(self.color_converter)
+                }
+            }),
+        )
+    }
+}
+
+impl<F, C, D> Component<D> for QrCode<F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    /// The largest square with whole pixel modules that fits.
+    fn measure(&self, available: Size) -> Size {
+        let (scale, quiet_zone) = self.fit(available);
+        Size::new_equal(scale * (self.modules() + 2 * quiet_zone))
+    }
+
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        QrCode {
+            top_left: area.top_left,
+            size: area.size,
+            ..self.clone()
+        }
+        .draw(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    use embedded_graphics::pixelcolor::BinaryColor;
+
+    const BOOKING_URL: &str = "https://rooms.example.com/book/4.12?from=display";
+
+    /// Monochrome frame buffer, `On` is dark.
+    struct Frame {
+        size: Size,
+        pixels: Vec<bool>,
+    }
+
+    impl Frame {
+        fn new(size: Size) -> Self {
+            Frame {
+                size,
+                pixels: vec![false; (size.width * size.height) as usize],
+            }
+        }
+
+        fn dark(&self, point: Point) -> bool {
+            self.pixels[(point.y as u32 * self.size.width + point.x as u32) as usize]
+        }
+    }
+
+    impl DrawTarget for Frame {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                assert!(self.bounding_box().contains(point), "drawn at {point:?}");
+                let index = (point.y as u32 * self.size.width + point.x as u32) as usize;
+                self.pixels[index] = color.is_on();
+            }
+            Ok(())
+        }
+    }
+
+    impl OriginDimensions for Frame {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    type BinaryCode = QrCode<fn(UnifiedColor) -> BinaryColor, BinaryColor>;
+
+    fn binary(color: UnifiedColor) -> BinaryColor {
+        match color {
+            UnifiedColor::White => BinaryColor::Off,
+            UnifiedColor::Black | UnifiedColor::Chromatic => BinaryColor::On,
+        }
+    }
+
+    /// Draws the booking URL into a dark frame of `frame` pixels, in a box of
+    /// `size` at `top_left`.
+    fn render(frame: Size, top_left: Point, size: Size) -> (BinaryCode, Frame) {
+        let code = QrCode::new(
+            top_left,
+            size,
+            BOOKING_URL,
+            ErrorCorrection::Medium,
+            binary as fn(_) -> _,
+        )
+        .unwrap();
+        let mut display = Frame::new(frame);
+        display.pixels.fill(true);
+        code.draw(&mut display).unwrap();
+        (code, display)
+    }
+
+    /// Checks every pixel of the box against the qrcodegen symbol: modules
+    /// are `scale` pixels square, surrounded by `quiet_zone` light modules.
+    fn assert_matches_symbol(code: &BinaryCode, frame: &Frame, scale: u32, quiet_zone: u32) {
+        let symbol = Symbol::encode_text(BOOKING_URL, QrCodeEcc::Medium).unwrap();
+        let modules = symbol.size();
+        assert_eq!(code.modules(), modules as u32);
+        assert_eq!(code.scale(), scale);
+
+        let area = code.symbol_area();
+        assert_eq!(area.size, Size::new_equal(modules as u32 * scale));
+        let zone = area.offset((quiet_zone * scale) as i32);
+        let boxed = Rectangle::new(code.top_left, code.size);
+        assert!(boxed.contains(zone.top_left));
+        assert!(boxed.contains(zone.bottom_right().unwrap()));
+
+        for point in boxed.points() {
+            let expected = if area.contains(point) {
+                let module = (point - area.top_left) / scale as i32;
+                symbol.get_module(module.x, module.y)
+            } else {
+                false
+            };
+            assert_eq!(frame.dark(point), expected, "pixel {point:?}");
+        }
+    }
+
+    #[test]
+    fn renders_the_symbol_with_quiet_zone() {
+        let (code, frame) = render(Size::new(200, 160), Point::new(20, 10), Size::new(170, 140));
+        let modules = code.modules();
+        let scale = 140 / (modules + 8);
+        assert!(scale >= 2, "test box too small for scale 2");
+        assert_matches_symbol(&code, &frame, scale, 4);
+        // Centered in the box
+        let area = code.symbol_area();
+        let (left, right) = (area.top_left.x - 20, 189 - area.bottom_right().unwrap().x);
+        assert!((left - right).abs() <= 1, "{left} vs {right}");
+        // Nothing outside the box is touched
+        assert!(frame.dark(Point::new(19, 10)) && frame.dark(Point::new(20, 9)));
+    }
+
+    #[test]
+    fn drops_the_quiet_zone_in_small_boxes() {
+        let modules = render(Size::new(1, 1), Point::zero(), Size::zero())
+            .0
+            .modules();
+        let side = modules + 4;
+        let (code, frame) = render(Size::new(side, side), Point::zero(), Size::new_equal(side));
+        assert_matches_symbol(&code, &frame, 1, 0);
+    }
+
+    #[test]
+    fn draws_only_the_background_when_too_small() {
+        let (code, frame) = render(Size::new(20, 20), Point::zero(), Size::new(20, 20));
+        assert_eq!(code.scale(), 0);
+        assert!(frame.pixels.iter().all(|&dark| !dark));
+    }
+}
@@ -0,0 +1,279 @@
+use std::marker::PhantomData;
+
+use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Line, PrimitiveStyleBuilder, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use crate::component::Component;
+use crate::event::{Event, format_time};
+use crate::text::{Scaled, truncate, wrap};
+use crate::theme::Theme;
+use crate::unified_color::UnifiedColor;
+
+const UPCOMING: usize = 2;
+
+/// Door sign for a single room: FREE/BUSY readable from across the corridor,
+/// the running meeting and what comes next.
+#[derive(Clone)]
+pub struct RoomStatus<'a, F, C> {
+    top_left: Point,
+    size: Size,
+    room_name: &'a str,
+    events: &'a [Event<'a>],
+    today: &'a str,
+    now: f32,
+    theme: Theme,
+
+    color_converter: F,
+    _phantom_color: PhantomData<C>,
+}
+
+impl<'a, F, C> RoomStatus<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+{
+    pub fn new(
+        top_left: Point,
+        size: Size,
+        room_name: &'a str,
+        events: &'a [Event<'a>],
+        today: &'a str,
+        now: f32,
+        color_converter: F,
+    ) -> Self {
+        RoomStatus {
+            top_left,
+            size,
+            room_name,
+            events,
+            today,
+            now,
+            theme: Theme::default(),
+            color_converter,
+            _phantom_color: PhantomData,
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Meeting running right now, the earliest one if they overlap.
+    pub fn current(&self) -> Option<&'a Event<'a>> {
+        self.events
+            .iter()
+            .filter(|event| event.is_running(self.today, self.now))
+            .min_by(|a, b| a.start.total_cmp(&b.start))
+    }
+
+    /// Today's meetings that haven't started yet, in order.
+    pub fn upcoming(&self) -> Vec<&'a Event<'a>> {
+        let mut upcoming: Vec<_> = self
+            .events
+            .iter()
+            .filter(|event| event.date == self.today && event.start > self.now)
+            .collect();
+        upcoming.sort_by(|a, b| a.start.total_cmp(&b.start));
+        upcoming
+    }
+
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let theme = &self.theme;
+        let font_width = theme.font_width();
+        let font_height = theme.font_height();
+        let small_font_width = theme.small_font_width();
+        let small_font_height = theme.small_font_height();
+        let padding = theme.padding;
+        let banner_scale = theme.banner_scale as i32;
+        let width = self.size.width as i32;
+        let text_width = width - 2 * padding;
+        let left = self.top_left.x + padding;
+        let current = self.current();
+        let upcoming = self.upcoming();
+
+        Rectangle::new(self.top_left, self.size)
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(UnifiedColor::White.into_with(self.color_converter))
+                    .build(),
+            )
+            .draw(display)?;
+
+        let text_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.font)
+            .text_color(UnifiedColor::Black.into_with(self.color_converter))
+            .build();
+        let text_small_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.small_font)
+            .text_color(UnifiedColor::Black.into_with(self.color_converter))
+            .build();
+        let max_chars = (text_width / font_width).max(1) as usize;
+        let max_small_chars = (text_width / small_font_width).max(1) as usize;
+
+        // Room name
+        let mut y = self.top_left.y + padding;
+        Text::with_baseline(
+            &truncate(self.room_name, max_chars),
+            Point::new(left, y),
+            text_style_black,
+            Baseline::Top,
+        )
+        .draw(display)?;
+        y += font_height + padding;
+
+        // FREE/BUSY banner
+        let banner_height = font_height * banner_scale + 2 * padding;
+        let (label, banner_color) = match current {
+            Some(_) => ("BUSY", Theme::accent(theme.accents.busy)),
+            None => ("FREE", UnifiedColor::Black),
+        };
+        Rectangle::new(
+            Point::new(self.top_left.x, y),
+            Size::new(self.size.width, banner_height as u32),
+        )
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .fill_color(banner_color.into_with(self.color_converter))
+                .build(),
+        )
+        .draw(display)?;
+
+        let banner_style: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(theme.font)
+            .text_color(UnifiedColor::White.into_with(self.color_converter))
+            .build();
+        let label_width = label.len() as i32 * font_width * banner_scale;
+        let mut banner = Scaled::new(
+            display,
+            Point::new(self.top_left.x + (width - label_width) / 2, y + padding),
+            theme.banner_scale,
+        );
+        Text::with_baseline(label, Point::zero(), banner_style, Baseline::Top).draw(&mut banner)?;
+        y += banner_height + padding;
+
+        // Running meeting, or how long the room stays free
+        match current {
+            Some(event) => {
+                for line in wrap(event.title, max_chars).iter().take(2) {
+                    Text::with_baseline(line, Point::new(left, y), text_style_black, Baseline::Top)
+                        .draw(display)?;
+                    y += font_height;
+                }
+                let until = match event.organizer {
+                    Some(organizer) => format!("until {} - {organizer}", format_time(event.end)),
+                    None => format!("until {}", format_time(event.end)),
+                };
+                Text::with_baseline(
+                    &truncate(&until, max_small_chars),
+                    Point::new(left, y),
+                    text_small_style_black,
+                    Baseline::Top,
+                )
+                .draw(display)?;
+                y += small_font_height;
+            }
+            None => {
+                let free = match upcoming.first() {
+                    Some(next) => format!("Free until {}", format_time(next.start)),
+                    None => "Free for the rest of the day".to_string(),
+                };
+                Text::with_baseline(
+                    &truncate(&free, max_chars),
+                    Point::new(left, y),
+                    text_style_black,
+                    Baseline::Top,
+                )
+                .draw(display)?;
+                y += font_height;
+            }
+        }
+        y += padding;
+
+        Line::new(
+            Point::new(self.top_left.x, y),
+            Point::new(self.top_left.x + width - 1, y),
+        )
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .stroke_color(UnifiedColor::Black.into_with(self.color_converter))
+                .stroke_width(theme.line_width)
+                .build(),
+        )
+        .draw(display)?;
+        y += padding;
+
+        // Next meetings
+        let bottom = self.top_left.y + self.size.height as i32 - padding;
+        if upcoming.is_empty() {
+            let centered = TextStyleBuilder::new()
+                .alignment(Alignment::Center)
+                .baseline(Baseline::Top)
+                .build();
+            Text::with_text_style(
+                "No more meetings today",
+                Point::new(self.top_left.x + width / 2, y),
+                text_small_style_black,
+                centered,
+            )
+            .draw(display)?;
+            return Ok(());
+        }
+
+        for event in upcoming.iter().take(UPCOMING) {
+            if y + font_height > bottom {
+                break;
+            }
+            let range = format!("{}-{}", format_time(event.start), format_time(event.end));
+            let line = format!("{range} {}", event.title);
+            Text::with_baseline(
+                &truncate(&line, max_chars),
+                Point::new(left, y),
+                text_style_black,
+                Baseline::Top,
+            )
+            .draw(display)?;
+            y += font_height;
+
+            if let Some(organizer) = event.organizer {
+                if y + small_font_height > bottom {
+                    break;
+                }
+                let indent = (range.len() as i32 + 1) * font_width;
+                let chars = ((text_width - indent) / small_font_width).max(1) as usize;
+                Text::with_baseline(
+                    &truncate(organizer, chars),
+                    Point::new(left + indent, y),
+                    text_small_style_black,
+                    Baseline::Top,
+                )
+                .draw(display)?;
+                y += small_font_height;
+            }
+            y += padding / 2;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, F, C, D> Component<D> for RoomStatus<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        RoomStatus {
+            top_left: area.top_left,
+            size: area.size,
+            ..self.clone()
+        }
+        .draw(display)
+    }
+}
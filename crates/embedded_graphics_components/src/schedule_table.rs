@@ -1,32 +1,318 @@
+use std::borrow::Cow;
+use std::fmt;
 use std::marker::PhantomData;
 
-use embedded_graphics::mono_font::ascii::{FONT_6X12, FONT_10X20};
-use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
+use embedded_graphics::mono_font::{MonoFont, MonoTextStyle, MonoTextStyleBuilder};
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::{
-    CornerRadiiBuilder, Line, PrimitiveStyleBuilder, Rectangle, RoundedRectangle,
+    CornerRadiiBuilder, Line, PrimitiveStyleBuilder, Rectangle, RoundedRectangle, Triangle,
 };
-use embedded_graphics::text::Text;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
 
+use crate::component::Component;
+use crate::event::{Event, EventIssue, lanes, validate};
+use crate::text::{CENTERED, truncate, wrap};
+use crate::theme::Theme;
+use crate::time_grid::{SlotLength, TimeGrid};
 use crate::unified_color::UnifiedColor;
 
-const FONT_HEIGHT: i32 = FONT_10X20.character_size.height as i32;
-const FONT_WIDTH: i32 = FONT_10X20.character_size.width as i32;
-const SMALL_FONT_HEIGHT: i32 = FONT_6X12.character_size.height as i32;
-const SMALL_FONT_WIDTH: i32 = FONT_6X12.character_size.width as i32;
+const MINOR_LINE_DOT_SPACING: usize = 4;
+/// Events shorter than this are drawn as a bar.
+const MIN_EVENT_HEIGHT: i32 = 3;
 
+/// Which events a [`ScheduleTable`] column shows: those of a date, of a
+/// resource (a room or person) on any date, or of a resource on a date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column<'a> {
+    pub date: Option<Cow<'a, str>>,
+    pub resource: Option<Cow<'a, str>>,
+    /// Shown instead of the resource or date.
+    pub header: Option<Cow<'a, str>>,
+    /// Drawn thin when none of the events fall in the column.
+    pub collapsible: bool,
+}
+
+impl<'a> Column<'a> {
+    /// Events on `date`, in the format of [`Event::date`].
+    pub fn date(date: impl Into<Cow<'a, str>>) -> Self {
+        Column {
+            date: Some(date.into()),
+            resource: None,
+            header: None,
+            collapsible: false,
+        }
+    }
+
+    /// Events of `resource`, see [`Event::resource`].
+    pub fn resource(resource: impl Into<Cow<'a, str>>) -> Self {
+        Column {
+            date: None,
+            resource: Some(resource.into()),
+            header: None,
+            collapsible: false,
+        }
+    }
+
+    /// Only the events on `date`.
+    pub fn on(mut self, date: impl Into<Cow<'a, str>>) -> Self {
+        self.date = Some(date.into());
+        self
+    }
+
+    pub fn with_header(mut self, header: impl Into<Cow<'a, str>>) -> Self {
+        self.header = Some(header.into());
+        self
+    }
+
+    /// Makes the column [`Self::collapsible`].
+    pub fn collapsed_when_empty(mut self) -> Self {
+        self.collapsible = true;
+        self
+    }
+
+    pub fn matches(&self, event: &Event<'_>) -> bool {
+        self.date.as_deref().is_none_or(|date| date == event.date)
+            && self
+                .resource
+                .as_deref()
+                .is_none_or(|resource| Some(resource) == event.resource)
+    }
+
+    /// The header, else the resource name, else the date.
+    pub fn header(&self) -> &str {
+        self.header
+            .as_deref()
+            .or(self.resource.as_deref())
+            .or(self.date.as_deref())
+            .unwrap_or("")
+    }
+}
+
+/// Why [`ScheduleTableBuilder::build`] refused the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleTableError {
+    /// No columns were given.
+    NoColumns,
+    /// The header leaves no room for the grid.
+    HeaderTooTall { header_height: u32, height: u32 },
+    /// Less than a pixel per time slot.
+    RowsDontFit { rows: u32, available: u32 },
+    /// The columns are too narrow for an event box.
+    ColumnsTooNarrow { columns: u32, available: u32 },
+}
+
+impl fmt::Display for ScheduleTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ScheduleTableError::NoColumns => write!(f, "schedule table has no columns"),
+            ScheduleTableError::HeaderTooTall {
+                header_height,
+                height,
+            } => write!(
+                f,
+                "header of {header_height}px doesn't fit a table of {height}px"
+            ),
+            ScheduleTableError::RowsDontFit { rows, available } => {
+                write!(f, "{rows} time slots don't fit in {available}px")
+            }
+            ScheduleTableError::ColumnsTooNarrow { columns, available } => {
+                write!(f, "{columns} columns don't fit in {available}px")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleTableError {}
+
+/// Named settings for a [`ScheduleTable`]. Header height and time column
+/// width default to what the theme's font needs.
+pub struct ScheduleTableBuilder<'a, F, C> {
+    top_left: Point,
+    size: Size,
+    header_height: Option<u32>,
+    time_col_width: Option<u32>,
+    time_header: &'a str,
+    columns: Vec<Column<'a>>,
+    today: Option<&'a str>,
+    nowline_time: Option<f32>,
+    time_grid: TimeGrid,
+    fit: Option<(u8, u8)>,
+    events: &'a [Event<'a>],
+    theme: Theme,
+
+    color_converter: F,
+    _phantom_color: PhantomData<C>,
+}
+
+impl<'a, F, C> ScheduleTableBuilder<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+{
+    pub fn header_height(mut self, header_height: u32) -> Self {
+        self.header_height = Some(header_height);
+        self
+    }
+
+    pub fn time_col_width(mut self, time_col_width: u32) -> Self {
+        self.time_col_width = Some(time_col_width);
+        self
+    }
+
+    /// Header of the time column, `"Time"` by default.
+    pub fn time_header(mut self, time_header: &'a str) -> Self {
+        self.time_header = time_header;
+        self
+    }
+
+    /// One column per date, in the format of [`Event::date`].
+    pub fn dates(mut self, dates: &[&'a str]) -> Self {
+        self.columns = dates.iter().map(|&date| Column::date(date)).collect();
+        self
+    }
+
+    /// One column per resource, headed by its name. Events of all dates are
+    /// shown, see [`Self::columns`] for a single day.
+    pub fn resources(mut self, resources: &[&'a str]) -> Self {
+        self.columns = resources
+            .iter()
+            .map(|&resource| Column::resource(resource))
+            .collect();
+        self
+    }
+
+    /// Columns by any key, e.g. rooms on one day for a lobby board:
+    /// `Column::resource(room).on(today)`, or the days of a
+    /// [`Week`](crate::week::Week).
+    pub fn columns(mut self, columns: impl IntoIterator<Item = Column<'a>>) -> Self {
+        self.columns = columns.into_iter().collect();
+        self
+    }
+
+    /// Date the now-line is drawn in, the first column's by default.
+    pub fn today(mut self, date: &'a str) -> Self {
+        self.today = Some(date);
+        self
+    }
+
+    /// Draws the now-line at `time`.
+    pub fn now(mut self, time: f32) -> Self {
+        self.nowline_time = Some(time);
+        self
+    }
+
+    /// Visible hours, 6:00 to 18:00 by default.
+    pub fn time_grid(mut self, time_grid: TimeGrid) -> Self {
+        self.time_grid = time_grid;
+        self
+    }
+
+    /// Fits the visible hours to the events shown in the columns, see
+    /// [`TimeGrid::fit_to`].
+    pub fn fit_time_range(mut self, padding: u8, min_hours: u8) -> Self {
+        self.fit = Some((padding, min_hours));
+        self
+    }
+
+    pub fn events(mut self, events: &'a [Event<'a>]) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn build(self) -> Result<ScheduleTable<'a, F, C>, ScheduleTableError> {
+        let theme = self.theme;
+        if self.columns.is_empty() {
+            return Err(ScheduleTableError::NoColumns);
+        }
+
+        // Events come from the network, draw only what makes sense
+        let issues = validate(self.events);
+        let events: Vec<Event<'a>> = self.events.iter().filter_map(Event::normalized).collect();
+
+        let time_grid = match self.fit {
+            Some((padding, min_hours)) => {
+                let shown: Vec<Event<'a>> = events
+                    .iter()
+                    .filter(|event| self.columns.iter().any(|column| column.matches(event)))
+                    .copied()
+                    .collect();
+                self.time_grid.fit_to(&shown, padding, min_hours)
+            }
+            None => self.time_grid,
+        };
+
+        let header_height = self
+            .header_height
+            .unwrap_or((theme.font_height() + 2 * theme.padding) as u32);
+        let time_col_width = self.time_col_width.unwrap_or_else(|| {
+            let widest = time_grid
+                .labels()
+                .map(|(_, label)| label.len())
+                .chain([self.time_header.len()])
+                .max()
+                .unwrap_or(0);
+            (widest as i32 * theme.font_width() + 2 * theme.padding) as u32
+        });
+        // Collapsing every column would leave nothing to share the width
+        let mut collapsed: Vec<bool> = self
+            .columns
+            .iter()
+            .map(|column| column.collapsible && !events.iter().any(|event| column.matches(event)))
+            .collect();
+        if collapsed.iter().all(|&collapsed| collapsed) {
+            collapsed.fill(false);
+        }
+
+        check_fit(
+            self.size,
+            header_height,
+            time_grid.rows(),
+            time_col_width,
+            &collapsed,
+            &theme,
+        )?;
+
+        Ok(ScheduleTable {
+            top_left: self.top_left,
+            size: self.size,
+            header_height: header_height as i32,
+            time_col_width: time_col_width as i32,
+            time_header: self.time_header,
+            columns: self.columns,
+            collapsed,
+            today: self.today,
+            nowline_time: self.nowline_time,
+            time_grid,
+            events,
+            issues,
+            theme,
+            color_converter: self.color_converter,
+            _phantom_color: PhantomData,
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct ScheduleTable<'a, F, C> {
     top_left: Point,
     size: Size,
     header_height: i32,
     time_col_width: i32,
-    num_date_cols: i32,
-    num_data_rows: i32,
-    y_pos_offset: i32, // FIXME: MAGIC
-    nowline_time: f32,
-    header_texts: [&'a str; 4],
-    time_range: core::ops::RangeInclusive<u8>,
-    time_intervals: [(&'a str, f32, f32, &'a str); 12],
+    time_header: &'a str,
+    columns: Vec<Column<'a>>,
+    /// Per column, drawn thin.
+    collapsed: Vec<bool>,
+    today: Option<&'a str>,
+    nowline_time: Option<f32>,
+    time_grid: TimeGrid,
+    events: Vec<Event<'a>>,
+    issues: Vec<(usize, EventIssue)>,
+    theme: Theme,
 
     color_converter: F,             // Функция-конвертер
     _phantom_color: PhantomData<C>, // Используем PhantomData для типа Color
@@ -37,50 +323,191 @@ where
     F: (Fn(UnifiedColor) -> C) + Copy, // F - это функция, которая берет UnifiedColor и возвращает C
     C: PixelColor, // C - это тип цвета, который будет использоваться для отрисовки
 {
-    #[allow(clippy::too_many_arguments)] // This many arguments are justified for a schedule table
-    pub fn new(
+    /// Table filling `size` at `top_left`, set up through the returned
+    /// builder.
+    pub fn builder(
         top_left: Point,
         size: Size,
-        header_height: i32,
-        time_col_width: i32,
-        num_date_cols: i32,
-        num_data_rows: i32,
-        y_pos_offset: i32,
-        nowline_time: f32,
-        header_texts: [&'a str; 4],
-        time_range: core::ops::RangeInclusive<u8>,
-        time_intervals: [(&'a str, f32, f32, &'a str); 12],
-
-        //
         color_converter: F,
-    ) -> Self {
-        ScheduleTable {
+    ) -> ScheduleTableBuilder<'a, F, C> {
+        ScheduleTableBuilder {
             top_left,
             size,
-            header_height,
-            time_col_width,
-            num_date_cols,
-            num_data_rows,
-            y_pos_offset,
-            nowline_time,
-            header_texts,
-            time_range,
-            time_intervals,
-            //
+            header_height: None,
+            time_col_width: None,
+            time_header: "Time",
+            columns: Vec::new(),
+            today: None,
+            nowline_time: None,
+            time_grid: TimeGrid::hours(6, 18, SlotLength::Min60),
+            fit: None,
+            events: &[],
+            theme: Theme::default(),
             color_converter,
             _phantom_color: PhantomData,
         }
     }
 
+    /// Problems found in the events given to the builder, by index. Reversed
+    /// and out-of-day times are drawn corrected, events without valid times
+    /// are left out.
+    pub fn issues(&self) -> &[(usize, EventIssue)] {
+        &self.issues
+    }
+
+    /// The same table filling `area`, checked like [`ScheduleTableBuilder::build`]
+    /// checks the size it was given.
+    pub fn moved_to(&self, area: Rectangle) -> Result<Self, ScheduleTableError> {
+        check_fit(
+            area.size,
+            self.header_height as u32,
+            self.time_grid.rows(),
+            self.time_col_width as u32,
+            &self.collapsed,
+            &self.theme,
+        )?;
+        Ok(ScheduleTable {
+            top_left: area.top_left,
+            size: area.size,
+            ..self.clone()
+        })
+    }
+
+    fn num_cols(&self) -> i32 {
+        self.columns.len() as i32
+    }
+
+    /// First column showing `event`, counted from 1.
+    fn column(&self, event: &Event<'_>) -> Option<i32> {
+        self.columns
+            .iter()
+            .position(|column| column.matches(event))
+            .map(|i| i as i32 + 1)
+    }
+
+    /// Lane of each event within its column, see [`lanes`].
+    fn lanes(&self) -> Vec<(usize, usize)> {
+        let mut placed = vec![(0, 1); self.events.len()];
+        for col in 1..=self.num_cols() {
+            let (indices, events): (Vec<usize>, Vec<Event<'a>>) = self
+                .events
+                .iter()
+                .enumerate()
+                .filter(|(_, event)| self.column(event) == Some(col))
+                .map(|(i, event)| (i, *event))
+                .unzip();
+            for (i, lane) in indices.into_iter().zip(lanes(&events)) {
+                placed[i] = lane;
+            }
+        }
+        placed
+    }
+
+    /// First and last column the now-line crosses, those on today.
+    fn now_cols(&self) -> Option<(i32, i32)> {
+        let today = self.today.or(self.columns[0].date.as_deref());
+        let mut cols = (1..=self.num_cols())
+            .filter(|&col| self.columns[col as usize - 1].date.as_deref() == today);
+        let first = cols.next()?;
+        Some((first, cols.last().unwrap_or(first)))
+    }
+
+    /// Right end of the now-line, past the time column only when today
+    /// isn't shown.
+    fn now_line_end(&self) -> i32 {
+        match self.now_cols() {
+            Some((_, last)) => {
+                let (x, width) = self.col_bounds(last);
+                x + width
+            }
+            None => self.top_left.x + self.time_col_width,
+        }
+    }
+
+    /// Events of column `col` that start before / end after the visible hours.
+    fn overflow(&self, col: i32) -> (usize, usize) {
+        let events = || {
+            self.events
+                .iter()
+                .filter(move |event| self.column(event) == Some(col))
+        };
+        let above = events()
+            .filter(|event| event.start < self.time_grid.start_hour())
+            .count();
+        let below = events()
+            .filter(|event| event.end > self.time_grid.end_hour())
+            .count();
+        (above, below)
+    }
+
+    fn overflow_marker_height(&self) -> i32 {
+        self.theme.small_font_height() + 4
+    }
+
+    /// Width of the columns that aren't collapsed.
+    fn col_width(&self) -> i32 {
+        let thin = self
+            .collapsed
+            .iter()
+            .filter(|&&collapsed| collapsed)
+            .count() as i32;
+        (self.size.width as i32 - self.time_col_width - thin * collapsed_col_width(&self.theme))
+            / (self.num_cols() - thin)
+    }
+
+    /// Left edge and width of column `col`, counted from 1.
+    fn col_bounds(&self, col: i32) -> (i32, i32) {
+        let width = |i: usize| {
+            if self.collapsed[i] {
+                collapsed_col_width(&self.theme)
+            } else {
+                self.col_width()
+            }
+        };
+        let before: i32 = (0..col as usize - 1).map(width).sum();
+        (
+            self.top_left.x + self.time_col_width + before,
+            width(col as usize - 1),
+        )
+    }
+
+    fn row_height(&self) -> i32 {
+        (self.size.height as i32 - self.header_height) / self.time_grid.rows() as i32
+    }
+
+    /// Y of `hour` on the time axis.
+    fn time_y(&self, hour: f32) -> i32 {
+        (self.top_left.y as f32
+            + self.header_height as f32
+            + self.time_grid.row_offset(hour) * self.row_height() as f32) as i32
+    }
+
+    /// Band covered by the now-line at `time`. Moving the line dirties the
+    /// bands of both the old and the new time.
+    pub fn nowline_area(&self, time: f32) -> Rectangle {
+        let y = self.time_y(time);
+        let width = self.now_line_end() - self.top_left.x;
+        Rectangle::new(
+            Point::new(self.top_left.x, y - self.theme.now_line_width as i32),
+            Size::new(width as u32 + 1, self.theme.now_line_width * 2 + 1),
+        )
+        .intersection(&Rectangle::new(self.top_left, self.size))
+    }
+
     pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = C>,
     {
+        let theme = &self.theme;
+        let font_width = theme.font_width();
+        let font_height = theme.font_height();
+        let small_font_height = theme.small_font_height();
         let display_width = self.size.width as i32;
         let display_height = self.size.height as i32;
 
-        let date_col_width = (display_width - self.time_col_width) / self.num_date_cols;
-        let row_height = (display_height - self.header_height) / self.num_data_rows;
+        let row_height = self.row_height();
+        let grid_top = self.top_left.y + self.header_height;
+        let grid_bottom = self.top_left.y + display_height;
 
         // Clear the area of the schedule table with white
         Rectangle::new(self.top_left, self.size)
@@ -92,34 +519,34 @@ where
             .draw(display)?;
 
         let text_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
-            .font(&FONT_10X20)
+            .font(theme.font)
             .text_color(UnifiedColor::Black.into_with(self.color_converter))
             .build();
 
         let text_small_style_black: MonoTextStyle<C> = MonoTextStyleBuilder::new()
-            .font(&FONT_6X12)
+            .font(theme.small_font)
             .text_color(UnifiedColor::Black.into_with(self.color_converter))
             .background_color(UnifiedColor::White.into_with(self.color_converter))
             .build();
 
         let base_style = PrimitiveStyleBuilder::new()
             .stroke_color(UnifiedColor::Black.into_with(self.color_converter))
-            .stroke_width(1)
+            .stroke_width(theme.line_width)
             .build();
 
         let bold_line_style = PrimitiveStyleBuilder::new()
             .stroke_color(UnifiedColor::Black.into_with(self.color_converter))
-            .stroke_width(2)
+            .stroke_width(theme.bold_line_width)
             .build();
 
         let now_line_style = PrimitiveStyleBuilder::new()
-            .stroke_color(UnifiedColor::Chromatic.into_with(self.color_converter))
-            .stroke_width(4)
+            .stroke_color(Theme::accent(theme.accents.now_line).into_with(self.color_converter))
+            .stroke_width(theme.now_line_width)
             .build();
 
         let interval_style = PrimitiveStyleBuilder::new()
             .stroke_color(UnifiedColor::Black.into_with(self.color_converter))
-            .stroke_width(2)
+            .stroke_width(theme.bold_line_width)
             .fill_color(UnifiedColor::White.into_with(self.color_converter))
             .build();
 
@@ -132,23 +559,33 @@ where
         Line::new(
             Point::new(self.top_left.x, self.top_left.y + self.header_height),
             Point::new(
-                self.top_left.x + display_width,
+                self.top_left.x + display_width - 1,
                 self.top_left.y + self.header_height,
             ),
         )
         .into_styled(bold_line_style)
         .draw(display)?;
 
-        for i in 1..self.num_data_rows {
-            let y = self.top_left.y + self.header_height + i * row_height;
+        // Full hours solid, the slots in between dotted
+        for (row, major) in self.time_grid.lines() {
+            let y = self.top_left.y + self.header_height + row as i32 * row_height;
             // Only draw lines within the table's defined height
-            if y < self.top_left.y + display_height {
+            if y >= self.top_left.y + display_height {
+                continue;
+            }
+            if major {
                 Line::new(
                     Point::new(self.top_left.x, y),
-                    Point::new(self.top_left.x + display_width, y),
+                    Point::new(self.top_left.x + display_width - 1, y),
                 )
                 .into_styled(base_style)
                 .draw(display)?;
+            } else {
+                let black = UnifiedColor::Black.into_with(self.color_converter);
+                let dots = (self.top_left.x + self.time_col_width..self.top_left.x + display_width)
+                    .step_by(MINOR_LINE_DOT_SPACING)
+                    .map(|x| Pixel(Point::new(x, y), black));
+                display.draw_iter(dots)?;
             }
         }
 
@@ -157,146 +594,539 @@ where
             Point::new(self.top_left.x + self.time_col_width, self.top_left.y),
             Point::new(
                 self.top_left.x + self.time_col_width,
-                self.top_left.y + display_height,
+                self.top_left.y + display_height - 1,
             ),
         )
         .into_styled(base_style)
         .draw(display)?;
 
-        for i in 1..self.num_date_cols {
-            let x = self.top_left.x + self.time_col_width + i * date_col_width;
+        for col in 2..=self.num_cols() {
+            let (x, _) = self.col_bounds(col);
             Line::new(
                 Point::new(x, self.top_left.y),
-                Point::new(x, self.top_left.y + display_height),
+                Point::new(x, self.top_left.y + display_height - 1),
             )
             .into_styled(base_style)
             .draw(display)?;
         }
 
         // Header texts
-        for (i, &text) in self.header_texts.iter().enumerate() {
-            let col_x = if i == 0 {
-                self.top_left.x
-            } else {
-                self.top_left.x + self.time_col_width + (i - 1) as i32 * date_col_width
-            };
-            let col_width = if i == 0 {
-                self.time_col_width
+        let headers =
+            std::iter::once(self.time_header).chain(self.columns.iter().map(Column::header));
+        for (i, text) in headers.enumerate() {
+            let (col_x, width) = if i == 0 {
+                (self.top_left.x, self.time_col_width)
             } else {
-                date_col_width
+                self.col_bounds(i as i32)
             };
 
-            let text_width = text.len() as i32 * FONT_WIDTH;
-            let x_pos = col_x + (col_width / 2) - (text_width / 2);
-            let y_pos =
-                self.top_left.y + (self.header_height / 2) - (FONT_HEIGHT / 2) + self.y_pos_offset;
-
-            Text::new(text, Point::new(x_pos, y_pos), text_style_black).draw(display)?;
+            let cell = Rectangle::new(
+                Point::new(col_x, self.top_left.y),
+                Size::new(width as u32, self.header_height as u32),
+            );
+            let (font, lines) = self.header_lines(text, width);
+            let style = MonoTextStyleBuilder::new()
+                .font(font)
+                .text_color(UnifiedColor::Black.into_with(self.color_converter))
+                .build();
+            let line_height = font.character_size.height as i32;
+            let first_y = cell.center().y - (lines.len() as i32 - 1) * line_height / 2;
+            for (n, line) in lines.iter().enumerate() {
+                let center = Point::new(cell.center().x, first_y + n as i32 * line_height);
+                Text::with_text_style(line, center, style, CENTERED).draw(display)?;
+            }
         }
 
         // Time column texts
-        for (i, hour) in self.time_range.clone().enumerate() {
-            let text = format!("{hour:02}:00");
-            let row_y = self.top_left.y + self.header_height + i as i32 * row_height;
-
-            let text_width = text.len() as i32 * FONT_WIDTH;
-            let x_pos = self.top_left.x + (self.time_col_width / 2) - (text_width / 2);
-            let y_pos = row_y + (row_height / 2) + self.y_pos_offset - FONT_HEIGHT;
-
-            // Only draw if within data rows (0-indexed to num_data_rows-1)
-            if i < self.num_data_rows as usize {
-                Text::new(&text, Point::new(x_pos, y_pos), text_style_black).draw(display)?;
-            }
+        // Each label is centered on its hour, across all of the hour's slots
+        let hour_height = row_height * (60 / self.time_grid.slot().minutes()) as i32;
+        for (row, text) in self.time_grid.labels() {
+            let top = grid_top + row as i32 * row_height;
+            let cell = Rectangle::new(
+                Point::new(self.top_left.x, top),
+                Size::new(
+                    self.time_col_width as u32,
+                    hour_height.min(grid_bottom - top) as u32,
+                ),
+            );
+            // The last hour can be cut short, the label stays in the table
+            let center = Point::new(
+                cell.center().x,
+                cell.center().y.min(grid_bottom - 1 - font_height / 2),
+            );
+            Text::with_text_style(&text, center, text_style_black, CENTERED).draw(display)?;
         }
 
         // Time intervals
-        let radii = CornerRadiiBuilder::new().all(Size::new(10, 10)).build();
-        let start_time_f32 = *self.time_range.start() as f32;
-        for (date, start, end, text) in self.time_intervals {
-            let col_index = match date {
-                "01.01.2025" => 1,
-                "02.01.2025" => 2,
-                "03.01.2025" => 3,
-                _ => continue,
+        let radii = CornerRadiiBuilder::new()
+            .all(Size::new_equal(theme.corner_radius))
+            .build();
+        let lanes = self.lanes();
+        for (event, &(lane, lanes)) in self.events.iter().zip(&lanes) {
+            let Some(col_index) = self.column(event) else {
+                continue;
             };
+            let &Event {
+                start,
+                end,
+                title: text,
+                ..
+            } = event;
 
-            let col_x = self.top_left.x + self.time_col_width + (col_index - 1) * date_col_width;
+            // Overlapping events share the column side by side
+            let (col_x, col_width) = self.col_bounds(col_index);
+            let lane_x = |lane: usize| col_x + col_width * lane as i32 / lanes as i32;
+            let (col_x, col_width) = (lane_x(lane), lane_x(lane + 1) - lane_x(lane));
 
-            let rel_start = start - start_time_f32;
-            let rel_end = end - start_time_f32;
+            let window = self.time_grid.start_hour()..=self.time_grid.end_hour();
+            let visible = if start == end {
+                window.contains(&start)
+            } else {
+                start < *window.end() && end > *window.start()
+            };
+            if !visible {
+                continue;
+            }
 
-            let start_y =
-                self.top_left.y + self.header_height + (rel_start * row_height as f32) as i32;
-            let end_y = self.top_left.y + self.header_height + (rel_end * row_height as f32) as i32;
+            // Clipped to the grid, the overflow markers tell about the rest
+            let start_y = self.time_y(start).max(grid_top);
+            let end_y = self.time_y(end).min(grid_bottom);
+            let box_width = (col_width - 2 * theme.inset).max(1) as u32;
+            let box_height = end_y - start_y - 2 * theme.inset;
 
-            // Ensure interval is within the bounds of the table
-            if start_y < self.top_left.y + display_height
-                && end_y > self.top_left.y + self.header_height
-            {
-                RoundedRectangle::new(
-                    Rectangle::new(
-                        Point::new(col_x + 4, start_y + 4),
-                        Size::new(date_col_width as u32 - 8, (end_y - start_y) as u32 - 8),
-                    ),
-                    radii,
+            // Too short for a box, a bar shows where it is
+            if box_height < MIN_EVENT_HEIGHT {
+                let y = start_y.min(grid_bottom - MIN_EVENT_HEIGHT);
+                Rectangle::new(
+                    Point::new(col_x + theme.inset, y),
+                    Size::new(box_width, MIN_EVENT_HEIGHT as u32),
+                )
+                .into_styled(
+                    PrimitiveStyleBuilder::new()
+                        .fill_color(UnifiedColor::Black.into_with(self.color_converter))
+                        .build(),
                 )
-                .into_styled(interval_style)
                 .draw(display)?;
+                continue;
+            }
 
-                if (end - start) >= 0.5 {
-                    let text_width_approx = text.len() as i32 * FONT_WIDTH;
-                    let text_x = col_x + (date_col_width / 2) - (text_width_approx / 2);
-                    let text_y =
-                        start_y + (end_y - start_y) / 2 + self.y_pos_offset - (FONT_HEIGHT / 3);
-
-                    Text::new(text, Point::new(text_x, text_y), text_style_black).draw(display)?;
-                }
-
-                let top_time_y = start_y + self.y_pos_offset - (SMALL_FONT_HEIGHT / 2);
-                let bottom_time_y = end_y + self.y_pos_offset - (SMALL_FONT_HEIGHT);
-
-                let start_time_str =
-                    format!("{:02}:{:02}", start as i32, (start * 60.0) as i32 % 60);
-                let end_time_str = format!("{:02}:{:02}", end as i32, (end * 60.0) as i32 % 60);
-
-                let start_time_x = col_x + (end_time_str.len() as i32 * SMALL_FONT_WIDTH / 3);
+            let event_box = Rectangle::new(
+                Point::new(col_x + theme.inset, start_y + theme.inset),
+                Size::new(box_width, box_height as u32),
+            );
+            RoundedRectangle::new(event_box, radii)
+                .into_styled(interval_style)
+                .draw(display)?;
 
-                let end_time_x = col_x
-                    + (date_col_width - (end_time_str.len() as i32 * SMALL_FONT_WIDTH / 2))
-                    - (end_time_str.len() as i32 * SMALL_FONT_WIDTH / 2)
-                    - 8;
+            if event_box.size.height as i32 >= font_height + small_font_height {
+                let max_chars = (event_box.size.width as i32 / font_width).max(1) as usize;
+                Text::with_text_style(
+                    &truncate(text, max_chars),
+                    event_box.center(),
+                    text_style_black,
+                    CENTERED,
+                )
+                .draw(display)?;
+            }
 
-                Text::new(
-                    &start_time_str,
-                    Point::new(start_time_x, top_time_y),
+            // Start and end time sit on the top and bottom edge of the box,
+            // clear of the rounded corners. Times cut off by the visible
+            // hours are left to the overflow markers, those too wide for a
+            // narrow box are left out.
+            let format = self.time_grid.format();
+            let corner = theme.corner_radius as i32;
+            let fits = |label: &str| {
+                label.len() as i32 * theme.small_font_width() + 2 * corner
+                    <= event_box.size.width as i32
+            };
+            let start_label = format.format((start * 60.0).round() as u32);
+            let end_label = format.format((end * 60.0).round() as u32);
+            // Kept clear of the table's bottom edge
+            let box_bottom = (event_box.top_left.y + event_box.size.height as i32 - 1)
+                .min(grid_bottom - 1 - small_font_height / 2);
+            if start >= self.time_grid.start_hour() && fits(&start_label) {
+                Text::with_text_style(
+                    &start_label,
+                    Point::new(event_box.top_left.x + corner, event_box.top_left.y),
                     text_small_style_black,
+                    TextStyleBuilder::new()
+                        .alignment(Alignment::Left)
+                        .baseline(Baseline::Middle)
+                        .build(),
                 )
                 .draw(display)?;
-
-                Text::new(
-                    &end_time_str,
-                    Point::new(end_time_x, bottom_time_y),
+            }
+            if end <= self.time_grid.end_hour() && fits(&end_label) {
+                Text::with_text_style(
+                    &end_label,
+                    Point::new(
+                        event_box.top_left.x + event_box.size.width as i32 - 1 - corner,
+                        box_bottom,
+                    ),
                     text_small_style_black,
+                    TextStyleBuilder::new()
+                        .alignment(Alignment::Right)
+                        .baseline(Baseline::Middle)
+                        .build(),
                 )
                 .draw(display)?;
             }
         }
 
-        // Current time line
-        let now_line_y = (self.top_left.y as f32
-            + self.header_height as f32
-            + (self.nowline_time - start_time_f32) * row_height as f32)
-            as i32;
+        // Overflow markers
+        for col in 1..=self.num_cols() {
+            let (above, below) = self.overflow(col);
+            let (col_x, col_width) = self.col_bounds(col);
+            if above > 0 {
+                self.draw_overflow_marker(display, col_x, col_width, grid_top, true, above)?;
+            }
+            if below > 0 {
+                self.draw_overflow_marker(
+                    display,
+                    col_x,
+                    col_width,
+                    grid_bottom - self.overflow_marker_height(),
+                    false,
+                    below,
+                )?;
+            }
+        }
 
-        let line_end_x = self.top_left.x + self.time_col_width + date_col_width; // Line extends across 2 date columns
+        // Current time line, over the time column and today's columns
+        let visible = self.time_grid.start_hour()..=self.time_grid.end_hour();
+        if let Some(nowline_time) = self.nowline_time.filter(|time| visible.contains(time)) {
+            let now_line_y = self.time_y(nowline_time);
+            let mut segments = vec![(self.top_left.x, self.top_left.x + self.time_col_width)];
+            if let Some((first, _)) = self.now_cols() {
+                segments.push((self.col_bounds(first).0, self.now_line_end()));
+            }
+            for (start_x, end_x) in segments {
+                Line::new(
+                    Point::new(start_x, now_line_y),
+                    Point::new(end_x, now_line_y),
+                )
+                .into_styled(now_line_style)
+                .draw(display)?;
+            }
+        }
 
-        Line::new(
-            Point::new(self.top_left.x, now_line_y),
-            Point::new(line_end_x, now_line_y),
+        Ok(())
+    }
+
+    /// Header text for a column `width` wide, on one line or wrapped at
+    /// spaces, in the regular font where it fits, else in the small one.
+    /// Text that fits neither is cut to its first word, e.g. the day's name
+    /// in a collapsed column.
+    fn header_lines(&self, text: &str, width: i32) -> (&'static MonoFont<'static>, Vec<String>) {
+        let words = text.split_whitespace().count();
+        let chars =
+            |font: &MonoFont<'_>| (width / font.character_size.width as i32).max(0) as usize;
+        for font in [self.theme.font, self.theme.small_font] {
+            if chars(font) == 0 {
+                continue;
+            }
+            let lines = wrap(text, chars(font));
+            let whole_words = lines
+                .iter()
+                .map(|line| line.split_whitespace().count())
+                .sum::<usize>()
+                == words;
+            let height = lines.len() as i32 * font.character_size.height as i32;
+            if whole_words && height + self.theme.padding <= self.header_height {
+                return (font, lines);
+            }
+        }
+        let first_word = text.split_whitespace().next().unwrap_or("");
+        let cut = first_word
+            .chars()
+            .take(chars(self.theme.small_font))
+            .collect();
+        (self.theme.small_font, vec![cut])
+    }
+
+    /// Arrow and count in the right corner of a column, `up` for events
+    /// above the visible hours.
+    fn draw_overflow_marker<D>(
+        &self,
+        display: &mut D,
+        col_x: i32,
+        col_width: i32,
+        y: i32,
+        up: bool,
+        count: usize,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let height = self.overflow_marker_height();
+        let text = count.to_string();
+        let width = height + text.len() as i32 * self.theme.small_font_width() + 4;
+        let x = col_x + col_width - width - 2;
+
+        Rectangle::new(Point::new(x, y), Size::new(width as u32, height as u32))
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .fill_color(UnifiedColor::White.into_with(self.color_converter))
+                    .stroke_color(UnifiedColor::Black.into_with(self.color_converter))
+                    .stroke_width(self.theme.line_width)
+                    .build(),
+            )
+            .draw(display)?;
+
+        let half = height / 2;
+        let (tip, base) = if up {
+            (y + 3, y + height - 4)
+        } else {
+            (y + height - 4, y + 3)
+        };
+        Triangle::new(
+            Point::new(x + half, tip),
+            Point::new(x + 3, base),
+            Point::new(x + height - 3, base),
+        )
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .fill_color(
+                    Theme::accent(self.theme.accents.warnings).into_with(self.color_converter),
+                )
+                .build(),
+        )
+        .draw(display)?;
+
+        let text_style: MonoTextStyle<C> = MonoTextStyleBuilder::new()
+            .font(self.theme.small_font)
+            .text_color(UnifiedColor::Black.into_with(self.color_converter))
+            .build();
+        Text::with_baseline(
+            &text,
+            Point::new(x + height, y + half),
+            text_style,
+            Baseline::Middle,
         )
-        .into_styled(now_line_style)
         .draw(display)?;
 
         Ok(())
     }
 }
+
+/// Checks that a table of `size` leaves every time slot a pixel and every
+/// column room for an event box.
+fn check_fit(
+    size: Size,
+    header_height: u32,
+    rows: u32,
+    time_col_width: u32,
+    collapsed: &[bool],
+    theme: &Theme,
+) -> Result<(), ScheduleTableError> {
+    if header_height >= size.height {
+        return Err(ScheduleTableError::HeaderTooTall {
+            header_height,
+            height: size.height,
+        });
+    }
+    let available = size.height - header_height;
+    if rows == 0 || rows > available {
+        return Err(ScheduleTableError::RowsDontFit { rows, available });
+    }
+
+    let columns = collapsed.len() as u32;
+    let thin = collapsed.iter().filter(|&&collapsed| collapsed).count() as u32;
+    let available = size.width.saturating_sub(time_col_width);
+    let min_col_width = (2 * (theme.inset as u32 + theme.corner_radius)).max(1);
+    let shared = available.saturating_sub(thin * collapsed_col_width(theme) as u32);
+    if shared / (columns - thin) < min_col_width {
+        return Err(ScheduleTableError::ColumnsTooNarrow { columns, available });
+    }
+    Ok(())
+}
+
+/// Width of a column collapsed for having no events, two small characters.
+fn collapsed_col_width(theme: &Theme) -> i32 {
+    2 * theme.small_font_width() + theme.padding
+}
+
+impl<'a, F, C, D> Component<D> for ScheduleTable<'a, F, C>
+where
+    F: (Fn(UnifiedColor) -> C) + Copy,
+    C: PixelColor,
+    D: DrawTarget<Color = C>,
+{
+    /// Draws nothing into an area the table doesn't fit, see
+    /// [`ScheduleTable::moved_to`].
+    fn draw_in(&self, display: &mut D, area: Rectangle) -> Result<(), D::Error> {
+        match self.moved_to(area) {
+            Ok(table) => table.draw(display),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    use embedded_graphics::mock_display::MockDisplay;
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use proptest::prelude::*;
+
+    fn binary(color: UnifiedColor) -> BinaryColor {
+        match color {
+            UnifiedColor::White => BinaryColor::Off,
+            UnifiedColor::Black | UnifiedColor::Chromatic => BinaryColor::On,
+        }
+    }
+
+    type BinaryTable = ScheduleTable<'static, fn(UnifiedColor) -> BinaryColor, BinaryColor>;
+
+    fn table(size: Size) -> Result<BinaryTable, ScheduleTableError> {
+        ScheduleTable::builder(Point::zero(), size, binary as fn(_) -> _)
+            .dates(&["2024-01-01", "2024-01-02"])
+            .build()
+    }
+
+    #[test]
+    fn builder_rejects_small_sizes() {
+        assert!(table(Size::new(240, 160)).is_ok());
+        assert!(matches!(
+            table(Size::new(240, 30)),
+            Err(ScheduleTableError::HeaderTooTall { .. })
+        ));
+        assert!(matches!(
+            table(Size::new(240, 40)),
+            Err(ScheduleTableError::RowsDontFit { .. })
+        ));
+        assert!(matches!(
+            table(Size::new(40, 160)),
+            Err(ScheduleTableError::ColumnsTooNarrow { .. })
+        ));
+    }
+
+    #[test]
+    fn moving_checks_the_new_size() {
+        let table = table(Size::new(240, 160)).unwrap();
+        let moved = table
+            .moved_to(Rectangle::new(Point::new(10, 20), Size::new(200, 120)))
+            .unwrap();
+        assert_eq!(moved.top_left, Point::new(10, 20));
+        assert_eq!(moved.size, Size::new(200, 120));
+
+        for size in [Size::new(200, 30), Size::new(200, 40), Size::new(40, 120)] {
+            assert!(table.moved_to(Rectangle::new(Point::zero(), size)).is_err());
+        }
+    }
+
+    #[test]
+    fn draws_nothing_where_it_doesnt_fit() {
+        let table = table(Size::new(240, 160)).unwrap();
+        let mut display = MockDisplay::<BinaryColor>::new();
+        for size in [Size::new(60, 30), Size::new(60, 40), Size::new(20, 60)] {
+            table
+                .draw_in(&mut display, Rectangle::new(Point::new(2, 2), size))
+                .unwrap();
+        }
+        assert_eq!(display.affected_area(), Rectangle::zero());
+    }
+
+    static OUTSIDE: [Event<'static>; 6] = [
+        Event::new("2024-01-01", 7.0, 8.0, "Early"),
+        Event::new("2024-01-01", 8.5, 10.0, "Starts early"),
+        Event::new("2024-01-01", 12.0, 13.0, "Inside"),
+        Event::new("2024-01-01", 16.0, 18.0, "Ends late"),
+        Event::new("2024-01-01", 18.0, 19.0, "Late"),
+        Event::new("2024-01-02", 20.0, 21.0, "Late"),
+    ];
+
+    #[test]
+    fn counts_events_outside_a_fixed_window() {
+        let table =
+            ScheduleTable::builder(Point::zero(), Size::new(240, 160), binary as fn(_) -> _)
+                .dates(&["2024-01-01", "2024-01-02", "2024-01-03"])
+                .time_grid(TimeGrid::hours(9, 17, SlotLength::Min60))
+                .events(&OUTSIDE)
+                .build()
+                .unwrap();
+        assert_eq!(table.overflow(1), (2, 2));
+        assert_eq!(table.overflow(2), (0, 1));
+        assert_eq!(table.overflow(3), (0, 0));
+    }
+
+    #[test]
+    fn fitted_window_leaves_nothing_outside() {
+        let table =
+            ScheduleTable::builder(Point::zero(), Size::new(240, 400), binary as fn(_) -> _)
+                .dates(&["2024-01-01", "2024-01-02"])
+                .time_grid(TimeGrid::hours(9, 17, SlotLength::Min60))
+                .fit_time_range(0, 1)
+                .events(&OUTSIDE)
+                .build()
+                .unwrap();
+        assert_eq!(table.overflow(1), (0, 0));
+        assert_eq!(table.overflow(2), (0, 0));
+    }
+
+    /// Screen that records pixels drawn outside `area`.
+    struct Bounded {
+        area: Rectangle,
+        outside: Vec<Point>,
+    }
+
+    impl DrawTarget for Bounded {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            let area = self.area;
+            self.outside.extend(
+                pixels
+                    .into_iter()
+                    .map(|Pixel(point, _)| point)
+                    .filter(|&point| !area.contains(point)),
+            );
+            Ok(())
+        }
+    }
+
+    impl OriginDimensions for Bounded {
+        fn size(&self) -> Size {
+            Size::new(400, 300)
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn random_events_stay_inside_the_table(
+            raw in prop::collection::vec(
+                (
+                    prop::sample::select(vec!["01.02.2024", "02.02.2024", "31.02.2024", ""]),
+                    -30.0f32..50.0,
+                    -4.0f32..8.0,
+                ),
+                0..16,
+            ),
+            fit in any::<bool>(),
+        ) {
+            let events: Vec<Event<'_>> = raw
+                .iter()
+                .map(|&(date, start, length)| Event::new(date, start, start + length, "Meeting"))
+                .collect();
+            let area = Rectangle::new(Point::new(20, 10), Size::new(300, 220));
+            let mut builder = ScheduleTable::builder(area.top_left, area.size, binary as fn(_) -> _)
+                .dates(&["01.02.2024", "02.02.2024"])
+                .events(&events)
+                .now(12.5);
+            if fit {
+                builder = builder.fit_time_range(1, 4);
+            }
+            let table = builder.build().unwrap();
+
+            let mut display = Bounded { area, outside: Vec::new() };
+            table.draw(&mut display).unwrap();
+            prop_assert!(display.outside.is_empty(), "drawn at {:?}", &display.outside[..1]);
+        }
+    }
+}
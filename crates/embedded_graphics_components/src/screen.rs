@@ -0,0 +1,598 @@
+//! Screen layouts described in configuration instead of code. A [`Node`]
+//! names components, their parameters and how stacks share the space; any
+//! serde format works, e.g. as JSON:
+//!
+//! ```json
+//! { "type": "stack", "direction": "vertical", "spacing": 4, "children": [
+//!     { "type": "battery", "fixed": 6 },
+//!     { "type": "schedule", "flex": 1, "from": 7, "to": 19, "slot": 30 }
+//! ] }
+//! ```
+
+use std::fmt;
+
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use serde::Deserialize;
+
+use crate::agenda_list::AgendaList;
+use crate::battery_indicator::BatteryIndicator;
+use crate::component::Component;
+use crate::event::Event;
+use crate::image_box::{ImageBox, ImageError, ImageFit};
+use crate::layout::{Direction, Length, Stack, inset, split_with};
+use crate::qr_code::{ErrorCorrection, QrCode, QrCodeError};
+use crate::room_status::RoomStatus;
+use crate::schedule_table::{Column, ScheduleTable, ScheduleTableError};
+use crate::theme::Theme;
+use crate::time_grid::{SlotLength, TimeFormat, TimeGrid};
+use crate::unified_color::UnifiedColor;
+use crate::week::{DayFormat, Week, WeekLength, Weekday};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Node {
+    Stack {
+        direction: Direction,
+        #[serde(default)]
+        spacing: u32,
+        #[serde(default)]
+        padding: u32,
+        children: Vec<Child>,
+    },
+    Schedule(Box<ScheduleSpec>),
+    Agenda,
+    RoomStatus {
+        /// Overrides [`ScreenData::room_name`].
+        #[serde(default)]
+        name: Option<String>,
+    },
+    Battery,
+    /// [`ScreenData::logo`], e.g. in a header.
+    Logo {
+        #[serde(default)]
+        fit: ImageFit,
+    },
+    /// QR code of `url`, or of [`ScreenData::booking_url`] when not given.
+    QrCode {
+        #[serde(default)]
+        url: Option<String>,
+        #[serde(default)]
+        ecc: ErrorCorrection,
+    },
+}
+
+/// A stack child and its share of the stack: `fixed` pixels, a `flex`
+/// weight, or what it measures when neither is given.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Child {
+    #[serde(default)]
+    pub fixed: Option<u32>,
+    #[serde(default)]
+    pub flex: Option<u32>,
+    #[serde(flatten)]
+    pub node: Node,
+}
+
+impl Child {
+    pub fn length(&self) -> Length {
+        match (self.fixed, self.flex) {
+            (Some(fixed), _) => Length::Fixed(fixed),
+            (None, Some(weight)) => Length::Flex(weight),
+            (None, None) => Length::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ScheduleSpec {
+    /// First visible hour.
+    pub from: u8,
+    /// Hour the grid ends at.
+    pub to: u8,
+    pub slot: SlotLength,
+    pub format: TimeFormat,
+    /// Fit the hours to the events instead, keeping at least `to - from`.
+    pub fit: bool,
+    pub header_height: Option<u32>,
+    pub time_col_width: Option<u32>,
+    /// Rooms or people to show on [`ScreenData::today`], one column each,
+    /// instead of the date columns.
+    pub resources: Vec<String>,
+    /// Show 5 or 7 days of the week of [`ScreenData::today`] instead of the
+    /// date columns.
+    pub week: Option<WeekLength>,
+    pub first_day: Weekday,
+    /// Thin columns for weekend days without events.
+    pub collapse_weekend: bool,
+    /// Day names in the week headers, Monday first.
+    pub day_names: Option<[String; 7]>,
+    /// Week header pattern, see [`DayFormat`].
+    pub day_header: Option<String>,
+}
+
+impl Default for ScheduleSpec {
+    fn default() -> Self {
+        ScheduleSpec {
+            from: 6,
+            to: 18,
+            slot: SlotLength::Min60,
+            format: TimeFormat::H24,
+            fit: false,
+            header_height: None,
+            time_col_width: None,
+            resources: Vec::new(),
+            week: None,
+            first_day: Weekday::Monday,
+            collapse_weekend: false,
+            day_names: None,
+            day_header: None,
+        }
+    }
+}
+
+/// What the components show, filled in at render time.
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenData<'a> {
+    pub events: &'a [Event<'a>],
+    /// Columns of the schedule grid, unless it shows resources.
+    pub dates: &'a [&'a str],
+    pub today: &'a str,
+    pub now: f32,
+    pub room_name: &'a str,
+    pub battery_percent: u8,
+    /// BMP image for [`Node::Logo`].
+    pub logo: Option<&'a [u8]>,
+    /// Where to book the room, for [`Node::QrCode`].
+    pub booking_url: Option<&'a str>,
+}
+
+/// A [`Node`] that can't be laid out in the area it got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenError {
+    Schedule(ScheduleTableError),
+    /// A week schedule, but [`ScreenData::today`] isn't a `dd.mm.yyyy` date.
+    InvalidToday,
+    /// A [`Node::Logo`] without [`ScreenData::logo`].
+    NoLogo,
+    Logo(ImageError),
+    /// A [`Node::QrCode`] with neither its own url nor
+    /// [`ScreenData::booking_url`].
+    NoUrl,
+    QrCode(QrCodeError),
+}
+
+impl fmt::Display for ScreenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenError::Schedule(err) => write!(f, "schedule: {err}"),
+            ScreenError::InvalidToday => write!(f, "schedule: today isn't a dd.mm.yyyy date"),
+            ScreenError::NoLogo => write!(f, "logo: no image given"),
+            ScreenError::Logo(err) => write!(f, "logo: {err}"),
+            ScreenError::NoUrl => write!(f, "QR code: no url given"),
+            ScreenError::QrCode(err) => write!(f, "QR code: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ScreenError {}
+
+impl From<ScheduleTableError> for ScreenError {
+    fn from(err: ScheduleTableError) -> Self {
+        ScreenError::Schedule(err)
+    }
+}
+
+impl From<ImageError> for ScreenError {
+    fn from(err: ImageError) -> Self {
+        ScreenError::Logo(err)
+    }
+}
+
+impl From<QrCodeError> for ScreenError {
+    fn from(err: QrCodeError) -> Self {
+        ScreenError::QrCode(err)
+    }
+}
+
+impl Node {
+    /// Component tree for `area`. Components are built for the area they
+    /// end up in, so size checks like the schedule's apply to the real one.
+    pub fn build<'a, F, C, D>(
+        &'a self,
+        area: Rectangle,
+        data: &ScreenData<'a>,
+        theme: Theme,
+        color_converter: F,
+    ) -> Result<Box<dyn Component<D> + 'a>, ScreenError>
+    where
+        F: (Fn(UnifiedColor) -> C) + Copy + 'a,
+        C: PixelColor + 'a,
+        D: DrawTarget<Color = C> + 'a,
+    {
+        Ok(match self {
+            Node::Stack {
+                direction,
+                spacing,
+                padding,
+                children,
+            } => {
+                let lengths: Vec<Length> = children.iter().map(Child::length).collect();
+                // Auto children are built once more to be measured
+                let mut measure_error = None;
+                let areas = split_with(
+                    inset(area, *padding),
+                    *direction,
+                    *spacing,
+                    &lengths,
+                    |i, available| {
+                        let probe = Rectangle::new(area.top_left, available);
+                        match children[i]
+                            .node
+                            .build::<F, C, D>(probe, data, theme, color_converter)
+                        {
+                            Ok(child) => child.measure(available),
+                            Err(err) => {
+                                measure_error.get_or_insert(err);
+                                Size::zero()
+                            }
+                        }
+                    },
+                );
+                if let Some(err) = measure_error {
+                    return Err(err);
+                }
+
+                let mut stack = Stack::new(*direction).spacing(*spacing).padding(*padding);
+                for (child, child_area) in children.iter().zip(areas) {
+                    stack = stack.child(
+                        child.length(),
+                        child.node.build(child_area, data, theme, color_converter)?,
+                    );
+                }
+                Box::new(stack)
+            }
+            Node::Schedule(spec) => {
+                let mut builder = ScheduleTable::builder(area.top_left, area.size, color_converter)
+                    .dates(data.dates)
+                    .events(data.events)
+                    .now(data.now)
+                    .today(data.today)
+                    .time_grid(
+                        TimeGrid::hours(spec.from, spec.to, spec.slot).with_format(spec.format),
+                    )
+                    .theme(theme);
+                if !spec.resources.is_empty() {
+                    let columns: Vec<Column<'a>> = spec
+                        .resources
+                        .iter()
+                        .map(|resource| Column::resource(resource).on(data.today))
+                        .collect();
+                    builder = builder.columns(columns);
+                } else if let Some(length) = spec.week {
+                    let mut format = DayFormat::ENGLISH;
+                    if let Some(names) = &spec.day_names {
+                        format.names = names.each_ref().map(String::as_str);
+                    }
+                    if let Some(pattern) = &spec.day_header {
+                        format.pattern = pattern;
+                    }
+                    let week = Week::containing(data.today, spec.first_day)
+                        .ok_or(ScreenError::InvalidToday)?
+                        .with_length(length)
+                        .with_format(format)
+                        .with_collapsed_weekend(spec.collapse_weekend);
+                    builder = builder.columns(week.columns());
+                }
+                if spec.fit {
+                    builder = builder.fit_time_range(1, spec.to.saturating_sub(spec.from));
+                }
+                if let Some(header_height) = spec.header_height {
+                    builder = builder.header_height(header_height);
+                }
+                if let Some(time_col_width) = spec.time_col_width {
+                    builder = builder.time_col_width(time_col_width);
+                }
+                Box::new(builder.build()?)
+            }
+            Node::Agenda => Box::new(
+                AgendaList::new(area.top_left, area.size, data.events, color_converter)
+                    .with_theme(theme),
+            ),
+            Node::RoomStatus { name } => Box::new(
+                RoomStatus::new(
+                    area.top_left,
+                    area.size,
+                    name.as_deref().unwrap_or(data.room_name),
+                    data.events,
+                    data.today,
+                    data.now,
+                    color_converter,
+                )
+                .with_theme(theme),
+            ),
+            Node::Battery => Box::new(
+                BatteryIndicator::new(
+                    area.top_left,
+                    Size::new(area.size.width, BATTERY_HEIGHT),
+                    color_converter,
+                )
+                .with_theme(theme)
+                .with_level(data.battery_percent),
+            ),
+            Node::Logo { fit } => {
+                let logo = data.logo.ok_or(ScreenError::NoLogo)?;
+                Box::new(
+                    ImageBox::new(area.top_left, area.size, logo, color_converter)?.with_fit(*fit),
+                )
+            }
+            Node::QrCode { url, ecc } => {
+                let url = url
+                    .as_deref()
+                    .or(data.booking_url)
+                    .ok_or(ScreenError::NoUrl)?;
+                Box::new(QrCode::new(
+                    area.top_left,
+                    area.size,
+                    url,
+                    *ecc,
+                    color_converter,
+                )?)
+            }
+        })
+    }
+}
+
+/// Height a battery bar measures, when its stack doesn't fix one.
+const BATTERY_HEIGHT: u32 = 6;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    use embedded_graphics::pixelcolor::BinaryColor;
+
+    fn binary(color: UnifiedColor) -> BinaryColor {
+        match color {
+            UnifiedColor::White => BinaryColor::Off,
+            UnifiedColor::Black | UnifiedColor::Chromatic => BinaryColor::On,
+        }
+    }
+
+    /// Screen that records where pixels were drawn.
+    #[derive(Default)]
+    struct Recorder {
+        drawn: Vec<Point>,
+    }
+
+    impl DrawTarget for Recorder {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.drawn
+                .extend(pixels.into_iter().map(|Pixel(point, _)| point));
+            Ok(())
+        }
+    }
+
+    impl OriginDimensions for Recorder {
+        fn size(&self) -> Size {
+            Size::new(400, 300)
+        }
+    }
+
+    const DATES: [&str; 1] = ["01.01.2024"];
+
+    fn data() -> ScreenData<'static> {
+        ScreenData {
+            events: &[],
+            dates: &DATES,
+            today: "01.01.2024",
+            now: 12.0,
+            room_name: "Room",
+            battery_percent: 50,
+            logo: None,
+            booking_url: None,
+        }
+    }
+
+    fn build<'a>(
+        node: &'a Node,
+        data: &ScreenData<'a>,
+        size: Size,
+    ) -> Result<Box<dyn Component<Recorder> + 'a>, ScreenError> {
+        node.build(
+            Rectangle::new(Point::zero(), size),
+            data,
+            Theme::default(),
+            binary as fn(_) -> _,
+        )
+    }
+
+    fn node(json: &str) -> Node {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn reads_the_documented_example() {
+        let node = node(
+            r#"{ "type": "stack", "direction": "vertical", "spacing": 4, "children": [
+                { "type": "battery", "fixed": 6 },
+                { "type": "schedule", "flex": 1, "from": 7, "to": 19, "slot": 30 }
+            ] }"#,
+        );
+        let expected = Node::Stack {
+            direction: Direction::Vertical,
+            spacing: 4,
+            padding: 0,
+            children: vec![
+                Child {
+                    fixed: Some(6),
+                    flex: None,
+                    node: Node::Battery,
+                },
+                Child {
+                    fixed: None,
+                    flex: Some(1),
+                    node: Node::Schedule(Box::new(ScheduleSpec {
+                        from: 7,
+                        to: 19,
+                        slot: SlotLength::Min30,
+                        ..ScheduleSpec::default()
+                    })),
+                },
+            ],
+        };
+        assert_eq!(node, expected);
+        assert!(build(&node, &data(), Size::new(400, 300)).is_ok());
+    }
+
+    #[test]
+    fn schedule_defaults() {
+        let Node::Schedule(spec) = node(r#"{ "type": "schedule" }"#) else {
+            panic!("not a schedule");
+        };
+        assert_eq!(*spec, ScheduleSpec::default());
+        assert_eq!((spec.from, spec.to), (6, 18));
+        assert_eq!(spec.slot, SlotLength::Min60);
+        assert_eq!(spec.format, TimeFormat::H24);
+        assert!(!spec.fit);
+        assert!(spec.resources.is_empty());
+        assert_eq!(spec.week, None);
+        assert_eq!(spec.first_day, Weekday::Monday);
+    }
+
+    #[test]
+    fn rejects_unknown_slots() {
+        let err = serde_json::from_str::<Node>(r#"{ "type": "schedule", "slot": 45 }"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("expected 15, 30 or 60"), "{err}");
+    }
+
+    #[test]
+    fn child_lengths() {
+        let length = |json| serde_json::from_str::<Child>(json).unwrap().length();
+        assert_eq!(
+            length(r#"{ "type": "agenda", "fixed": 20 }"#),
+            Length::Fixed(20)
+        );
+        assert_eq!(
+            length(r#"{ "type": "agenda", "flex": 2 }"#),
+            Length::Flex(2)
+        );
+        assert_eq!(length(r#"{ "type": "agenda" }"#), Length::Auto);
+        // Fixed wins over flex
+        assert_eq!(
+            length(r#"{ "type": "agenda", "fixed": 20, "flex": 2 }"#),
+            Length::Fixed(20)
+        );
+    }
+
+    #[test]
+    fn auto_children_get_their_measured_size() {
+        let node = node(
+            r#"{ "type": "stack", "direction": "vertical", "spacing": 4, "children": [
+                { "type": "battery" },
+                { "type": "battery", "flex": 1 }
+            ] }"#,
+        );
+        let size = Size::new(100, 80);
+        let mut display = Recorder::default();
+        build(&node, &data(), size)
+            .unwrap()
+            .draw_in(&mut display, Rectangle::new(Point::zero(), size))
+            .unwrap();
+
+        // Distinct pixels, the battery fills over its background
+        let mut drawn = display.drawn;
+        drawn.sort_by_key(|point| (point.y, point.x));
+        drawn.dedup();
+        let rows = |y: i32| drawn.iter().filter(|point| point.y == y).count();
+        // The measured battery height, the gap, then the rest
+        for y in 0..BATTERY_HEIGHT as i32 {
+            assert_eq!(rows(y), 100, "row {y}");
+        }
+        for y in BATTERY_HEIGHT as i32..BATTERY_HEIGHT as i32 + 4 {
+            assert_eq!(rows(y), 0, "row {y}");
+        }
+        for y in BATTERY_HEIGHT as i32 + 4..80 {
+            assert_eq!(rows(y), 100, "row {y}");
+        }
+        assert_eq!(rows(80), 0);
+    }
+
+    #[test]
+    fn reports_what_cant_be_built() {
+        let size = Size::new(240, 160);
+        let data = data();
+
+        let long_url = "x".repeat(4000);
+        let qr = Node::QrCode {
+            url: Some(long_url),
+            ecc: ErrorCorrection::High,
+        };
+        assert!(matches!(
+            build(&qr, &data, size).err(),
+            Some(ScreenError::QrCode(QrCodeError::TooLong { len: 4000, .. }))
+        ));
+        let qr = Node::QrCode {
+            url: None,
+            ecc: ErrorCorrection::default(),
+        };
+        assert_eq!(build(&qr, &data, size).err(), Some(ScreenError::NoUrl));
+
+        let logo = Node::Logo {
+            fit: ImageFit::default(),
+        };
+        assert_eq!(build(&logo, &data, size).err(), Some(ScreenError::NoLogo));
+        let bad_logo = ScreenData {
+            logo: Some(b"not a bmp"),
+            ..data
+        };
+        assert!(matches!(
+            build(&logo, &bad_logo, size).err(),
+            Some(ScreenError::Logo(_))
+        ));
+
+        let schedule = node(r#"{ "type": "schedule" }"#);
+        assert!(matches!(
+            build(&schedule, &data, Size::new(240, 40)).err(),
+            Some(ScreenError::Schedule(
+                ScheduleTableError::RowsDontFit { .. }
+            ))
+        ));
+        let week = node(r#"{ "type": "schedule", "week": 5 }"#);
+        let no_date = ScreenData {
+            today: "today",
+            ..data
+        };
+        assert_eq!(
+            build(&week, &no_date, size).err(),
+            Some(ScreenError::InvalidToday)
+        );
+    }
+
+    #[test]
+    fn stacks_pass_on_child_errors() {
+        // Whether the child is measured first or not
+        for length in ["", r#", "flex": 1"#] {
+            let node = node(&format!(
+                r#"{{ "type": "stack", "direction": "horizontal", "children": [
+                    {{ "type": "agenda", "fixed": 40 }},
+                    {{ "type": "qr_code"{length} }}
+                ] }}"#
+            ));
+            assert_eq!(
+                build(&node, &data(), Size::new(240, 160)).err(),
+                Some(ScreenError::NoUrl)
+            );
+        }
+    }
+}
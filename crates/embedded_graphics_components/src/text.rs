@@ -0,0 +1,192 @@
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::{Alignment, Baseline, TextStyle, TextStyleBuilder};
+
+/// Centers text on its position both ways, so a label placed at
+/// `cell.center()` sits in the middle of the cell whatever the font.
+pub(crate) const CENTERED: TextStyle = TextStyleBuilder::new()
+    .alignment(Alignment::Center)
+    .baseline(Baseline::Middle)
+    .build();
+
+/// Greedy word wrap for monospace fonts. Explicit newlines are kept, words
+/// longer than a line are split.
+pub(crate) fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            while word.chars().count() > max_chars {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let split = word
+                    .char_indices()
+                    .nth(max_chars)
+                    .map_or(word.len(), |(i, _)| i);
+                lines.push(word[..split].to_string());
+                word = &word[split..];
+            }
+            if word.is_empty() {
+                continue;
+            }
+
+            let needed = if line.is_empty() {
+                word.chars().count()
+            } else {
+                line.chars().count() + 1 + word.chars().count()
+            };
+            if needed > max_chars {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Cuts `text` to `max_chars`, marking the cut with `...`.
+pub(crate) fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let keep = max_chars.saturating_sub(3);
+    let mut short: String = text.chars().take(keep).collect();
+    short.push_str(&"..."[..max_chars.min(3)]);
+    short
+}
+
+/// Draws everything `scale` times larger, for text bigger than the largest
+/// built-in font. Pixel `p` lands on `origin + p * scale`.
+pub(crate) struct Scaled<'d, D> {
+    target: &'d mut D,
+    origin: Point,
+    scale: u32,
+}
+
+impl<'d, D: DrawTarget> Scaled<'d, D> {
+    pub(crate) fn new(target: &'d mut D, origin: Point, scale: u32) -> Self {
+        Scaled {
+            target,
+            origin,
+            scale: scale.max(1),
+        }
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for Scaled<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let size = Size::new(self.scale, self.scale);
+        for Pixel(point, color) in pixels {
+            let top_left = self.origin + point * self.scale as i32;
+            self.target
+                .fill_solid(&Rectangle::new(top_left, size), color)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: DrawTarget> Dimensions for Scaled<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        let target = self.target.bounding_box();
+        Rectangle::new(
+            Point::zero(),
+            Size::new(
+                target.size.width / self.scale,
+                target.size.height / self.scale,
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+    use embedded_graphics::mono_font::ascii::{FONT_6X10, FONT_8X13, FONT_10X20};
+    use embedded_graphics::mono_font::{MonoFont, MonoTextStyle};
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::text::Text;
+
+    const FONTS: [&MonoFont<'static>; 3] = [&FONT_6X10, &FONT_8X13, &FONT_10X20];
+
+    /// Ink drawn for `text` centered on `center`.
+    fn ink(text: &str, font: &MonoFont<'_>, center: Point) -> Rectangle {
+        let mut display = MockDisplay::<BinaryColor>::new();
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        Text::with_text_style(text, center, style, CENTERED)
+            .draw(&mut display)
+            .unwrap();
+        display.affected_area()
+    }
+
+    fn assert_centered(ink: Rectangle, center: Point, what: &str) {
+        let bottom_right = ink.bottom_right().unwrap();
+        let (left, right) = (center.x - ink.top_left.x, bottom_right.x - center.x);
+        let (top, bottom) = (center.y - ink.top_left.y, bottom_right.y - center.y);
+        assert!(
+            (left - right).abs() <= 1,
+            "{what}: {ink:?} not centered on x"
+        );
+        assert!(
+            (top - bottom).abs() <= 1,
+            "{what}: {ink:?} not centered on y"
+        );
+    }
+
+    #[test]
+    fn centers_text_in_every_font() {
+        let center = Point::new(32, 32);
+        for font in FONTS {
+            for text in ["HH", "MON", "0:00", "Rm 12"] {
+                let what = format!("{text:?} in {:?}", font.character_size);
+                assert_centered(ink(text, font, center), center, &what);
+            }
+        }
+    }
+
+    #[test]
+    fn scaling_keeps_text_centered() {
+        let center = Point::new(10, 6);
+        let unscaled = ink("HI", &FONT_6X10, center);
+
+        let mut display = MockDisplay::<BinaryColor>::new();
+        let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+        let origin = Point::new(2, 12);
+        Text::with_text_style("HI", center, style, CENTERED)
+            .draw(&mut Scaled::new(&mut display, origin, 3))
+            .unwrap();
+
+        let scaled = display.affected_area();
+        assert_eq!(scaled.top_left, origin + unscaled.top_left * 3);
+        assert_eq!(scaled.size, unscaled.size * 3);
+    }
+
+    #[test]
+    fn wraps_words_and_splits_long_ones() {
+        assert_eq!(wrap("team sync today", 9), ["team sync", "today"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("one\ntwo", 20), ["one", "two"]);
+    }
+
+    #[test]
+    fn truncates_with_marker() {
+        assert_eq!(truncate("Standup", 10), "Standup");
+        assert_eq!(truncate("Quarterly review", 10), "Quarter...");
+        assert_eq!(truncate("Review", 2), "..");
+    }
+}
@@ -0,0 +1,133 @@
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::mono_font::ascii::{FONT_5X8, FONT_6X12, FONT_8X13, FONT_10X20};
+use embedded_graphics::prelude::*;
+
+use crate::unified_color::UnifiedColor;
+
+/// Elements drawn in the chromatic color. What is switched off is drawn
+/// black instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accents {
+    /// Current time line in the schedule.
+    pub now_line: bool,
+    /// Day headers, the error title bar and the device ID.
+    pub headings: bool,
+    /// The BUSY banner of the door sign.
+    pub busy: bool,
+    /// Low battery and events outside the visible hours.
+    pub warnings: bool,
+}
+
+impl Accents {
+    pub const ALL: Accents = Accents {
+        now_line: true,
+        headings: true,
+        busy: true,
+        warnings: true,
+    };
+
+    pub const NONE: Accents = Accents {
+        now_line: false,
+        headings: false,
+        busy: false,
+        warnings: false,
+    };
+}
+
+/// Fonts, strokes and spacing shared by all components, so the same
+/// component works on a large and a small panel.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// Headers, hour labels and event titles.
+    pub font: &'static MonoFont<'static>,
+    /// Times inside events, organizers, footers and counters.
+    pub small_font: &'static MonoFont<'static>,
+    /// Grid lines and borders.
+    pub line_width: u32,
+    /// Header separator and event outlines.
+    pub bold_line_width: u32,
+    pub now_line_width: u32,
+    /// Space between text and the edge of its area.
+    pub padding: i32,
+    /// Gap between an event box and its grid cell.
+    pub inset: i32,
+    pub corner_radius: u32,
+    /// Scale of the FREE/BUSY banner text.
+    pub banner_scale: u32,
+    pub accents: Accents,
+}
+
+impl Theme {
+    /// 7.5" panels, 800x480.
+    pub const LARGE: Theme = Theme {
+        font: &FONT_10X20,
+        small_font: &FONT_6X12,
+        line_width: 1,
+        bold_line_width: 2,
+        now_line_width: 4,
+        padding: 8,
+        inset: 4,
+        corner_radius: 10,
+        banner_scale: 3,
+        accents: Accents::ALL,
+    };
+
+    /// 2.9" panels, 296x128.
+    pub const SMALL: Theme = Theme {
+        font: &FONT_8X13,
+        small_font: &FONT_5X8,
+        line_width: 1,
+        bold_line_width: 1,
+        now_line_width: 2,
+        padding: 3,
+        inset: 2,
+        corner_radius: 4,
+        banner_scale: 2,
+        accents: Accents::ALL,
+    };
+
+    /// Preset for a display of `size`, in either rotation.
+    pub fn for_size(size: Size) -> Theme {
+        if size.width.min(size.height) < 200 {
+            Theme::SMALL
+        } else {
+            Theme::LARGE
+        }
+    }
+
+    pub fn with_accents(mut self, accents: Accents) -> Self {
+        self.accents = accents;
+        self
+    }
+
+    pub fn font_width(&self) -> i32 {
+        self.font.character_size.width as i32
+    }
+
+    pub fn font_height(&self) -> i32 {
+        self.font.character_size.height as i32
+    }
+
+    pub fn small_font_width(&self) -> i32 {
+        self.small_font.character_size.width as i32
+    }
+
+    pub fn small_font_height(&self) -> i32 {
+        self.small_font.character_size.height as i32
+    }
+
+    /// Chromatic if `enabled` is one of the [`Accents`], black otherwise.
+    pub fn accent(enabled: bool) -> UnifiedColor {
+        if enabled {
+            UnifiedColor::Chromatic
+        } else {
+            UnifiedColor::Black
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::LARGE
+    }
+}
@@ -0,0 +1,326 @@
+use serde::Deserialize;
+
+use crate::event::Event;
+
+/// Length of one grid row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "u32")]
+pub enum SlotLength {
+    Min15,
+    Min30,
+    Min60,
+}
+
+impl SlotLength {
+    pub fn minutes(self) -> u32 {
+        match self {
+            SlotLength::Min15 => 15,
+            SlotLength::Min30 => 30,
+            SlotLength::Min60 => 60,
+        }
+    }
+}
+
+/// Minutes: 15, 30 or 60.
+impl TryFrom<u32> for SlotLength {
+    type Error = String;
+
+    fn try_from(minutes: u32) -> Result<Self, Self::Error> {
+        match minutes {
+            15 => Ok(SlotLength::Min15),
+            30 => Ok(SlotLength::Min30),
+            60 => Ok(SlotLength::Min60),
+            other => Err(format!("slot of {other} minutes, expected 15, 30 or 60")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TimeFormat {
+    /// `13:30`
+    #[serde(rename = "24h")]
+    H24,
+    /// `1:30 PM`, full hours as `1 PM`.
+    #[serde(rename = "12h")]
+    H12,
+}
+
+impl TimeFormat {
+    pub fn format(self, minutes_of_day: u32) -> String {
+        let (hour, minute) = ((minutes_of_day / 60) % 24, minutes_of_day % 60);
+        match self {
+            TimeFormat::H24 => format!("{hour:02}:{minute:02}"),
+            TimeFormat::H12 => {
+                let suffix = if hour < 12 { "AM" } else { "PM" };
+                let hour = match hour % 12 {
+                    0 => 12,
+                    hour => hour,
+                };
+                if minute == 0 {
+                    format!("{hour} {suffix}")
+                } else {
+                    format!("{hour}:{minute:02} {suffix}")
+                }
+            }
+        }
+    }
+}
+
+/// Vertical time axis of a schedule: the visible window, split into slots.
+/// Every full hour is a major line and gets a label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeGrid {
+    /// Minutes since midnight, a multiple of the slot length.
+    start: u32,
+    end: u32,
+    slot: SlotLength,
+    format: TimeFormat,
+}
+
+impl TimeGrid {
+    /// `start..end` in full hours, `end` is not included.
+    pub fn hours(start: u8, end: u8, slot: SlotLength) -> Self {
+        let start = u32::from(start.min(23)) * 60;
+        let end = (u32::from(end.min(24)) * 60).max(start + 60);
+        TimeGrid {
+            start,
+            end,
+            slot,
+            format: TimeFormat::H24,
+        }
+    }
+
+    /// Smallest window of full hours that covers `events`, widened by
+    /// `padding` hours on both sides and to at least `min_hours`. Without
+    /// events the grid stays as it is.
+    pub fn fit_to(self, events: &[Event<'_>], padding: u8, min_hours: u8) -> Self {
+        let Some(first) = events.iter().map(|event| event.start).reduce(f32::min) else {
+            return self;
+        };
+        let last = events.iter().map(|event| event.end).fold(first, f32::max);
+
+        let padding = u32::from(padding);
+        let mut start = (first.clamp(0.0, 24.0).floor() as u32).saturating_sub(padding);
+        let mut end = (last.clamp(0.0, 24.0).ceil() as u32 + padding).min(24);
+
+        let min_hours = u32::from(min_hours).clamp(1, 24);
+        if end - start < min_hours {
+            let missing = min_hours - (end - start);
+            start = start.saturating_sub(missing / 2);
+            end = (start + min_hours).min(24);
+            start = end - min_hours;
+        }
+
+        TimeGrid {
+            start: start * 60,
+            end: end * 60,
+            ..self
+        }
+    }
+
+    pub fn with_format(mut self, format: TimeFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn slot(&self) -> SlotLength {
+        self.slot
+    }
+
+    pub fn format(&self) -> TimeFormat {
+        self.format
+    }
+
+    /// First visible hour, as in [`crate::event::Event`] times.
+    pub fn start_hour(&self) -> f32 {
+        self.start as f32 / 60.0
+    }
+
+    pub fn end_hour(&self) -> f32 {
+        self.end as f32 / 60.0
+    }
+
+    pub fn rows(&self) -> u32 {
+        (self.end - self.start).div_ceil(self.slot.minutes())
+    }
+
+    /// Position of `hour` in rows from the top, fractional inside a slot.
+    pub fn row_offset(&self, hour: f32) -> f32 {
+        (hour * 60.0 - self.start as f32) / self.slot.minutes() as f32
+    }
+
+    /// Lines between rows, `(row, major)`; the outer border is not included.
+    pub fn lines(&self) -> impl Iterator<Item = (u32, bool)> + '_ {
+        (1..self.rows()).map(|row| {
+            let minutes = self.start + row * self.slot.minutes();
+            (row, minutes % 60 == 0)
+        })
+    }
+
+    /// Labels for every full hour in the window, `(row, text)`.
+    pub fn labels(&self) -> impl Iterator<Item = (u32, String)> + '_ {
+        (0..self.rows()).filter_map(|row| {
+            let minutes = self.start + row * self.slot.minutes();
+            (minutes % 60 == 0).then(|| (row, self.format.format(minutes)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_24_hours() {
+        assert_eq!(TimeFormat::H24.format(0), "00:00");
+        assert_eq!(TimeFormat::H24.format(13 * 60 + 30), "13:30");
+        assert_eq!(TimeFormat::H24.format(24 * 60), "00:00");
+    }
+
+    #[test]
+    fn formats_12_hours() {
+        assert_eq!(TimeFormat::H12.format(0), "12 AM");
+        assert_eq!(TimeFormat::H12.format(30), "12:30 AM");
+        assert_eq!(TimeFormat::H12.format(9 * 60 + 5), "9:05 AM");
+        assert_eq!(TimeFormat::H12.format(12 * 60), "12 PM");
+        assert_eq!(TimeFormat::H12.format(12 * 60 + 45), "12:45 PM");
+        assert_eq!(TimeFormat::H12.format(23 * 60), "11 PM");
+    }
+
+    #[test]
+    fn counts_rows_from_the_window() {
+        assert_eq!(TimeGrid::hours(7, 19, SlotLength::Min30).rows(), 24);
+        assert_eq!(TimeGrid::hours(7, 19, SlotLength::Min15).rows(), 48);
+        assert_eq!(TimeGrid::hours(6, 18, SlotLength::Min60).rows(), 12);
+        // At least an hour, whatever is asked for
+        assert_eq!(TimeGrid::hours(10, 10, SlotLength::Min60).rows(), 1);
+        assert_eq!(TimeGrid::hours(0, 30, SlotLength::Min60).rows(), 24);
+    }
+
+    #[test]
+    fn splits_lines_into_major_and_minor() {
+        for (slot, per_hour) in [
+            (SlotLength::Min15, 4),
+            (SlotLength::Min30, 2),
+            (SlotLength::Min60, 1),
+        ] {
+            let grid = TimeGrid::hours(8, 12, slot);
+            let lines: Vec<(u32, bool)> = grid.lines().collect();
+            // Between rows only, the border isn't a line
+            assert_eq!(lines.len() as u32, grid.rows() - 1, "{slot:?}");
+            let major = lines.iter().filter(|(_, major)| *major).count();
+            assert_eq!(major, 3, "{slot:?}");
+            assert_eq!(lines.len() - major, 4 * (per_hour - 1) as usize, "{slot:?}");
+            for (row, major) in lines {
+                assert_eq!(major, row % per_hour == 0, "{slot:?} row {row}");
+            }
+        }
+    }
+
+    #[test]
+    fn labels_every_full_hour() {
+        let grid = TimeGrid::hours(7, 19, SlotLength::Min30);
+        let labels: Vec<(u32, String)> = grid.labels().collect();
+        assert_eq!(labels.len(), 12);
+        assert_eq!(labels[0], (0, "07:00".to_string()));
+        assert_eq!(labels[1], (2, "08:00".to_string()));
+        assert_eq!(labels[11], (22, "18:00".to_string()));
+
+        let grid = TimeGrid::hours(11, 14, SlotLength::Min15).with_format(TimeFormat::H12);
+        let labels: Vec<(u32, String)> = grid.labels().collect();
+        assert_eq!(
+            labels,
+            [
+                (0, "11 AM".to_string()),
+                (4, "12 PM".to_string()),
+                (8, "1 PM".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_only_known_slots() {
+        assert_eq!(SlotLength::try_from(15), Ok(SlotLength::Min15));
+        assert_eq!(SlotLength::try_from(60), Ok(SlotLength::Min60));
+        assert!(SlotLength::try_from(45).is_err());
+        assert!(SlotLength::try_from(0).is_err());
+    }
+
+    fn event(start: f32, end: f32) -> Event<'static> {
+        Event::new("2024-01-01", start, end, "Meeting")
+    }
+
+    fn window(grid: TimeGrid) -> (f32, f32) {
+        (grid.start_hour(), grid.end_hour())
+    }
+
+    #[test]
+    fn fitting_covers_the_events() {
+        let grid = TimeGrid::hours(8, 18, SlotLength::Min30);
+        assert_eq!(window(grid.fit_to(&[], 1, 4)), (8.0, 18.0));
+
+        // A late event widens the window past the configured end
+        let events = [event(9.0, 10.0), event(19.0, 20.0)];
+        assert_eq!(window(grid.fit_to(&events, 0, 1)), (9.0, 20.0));
+        assert_eq!(window(grid.fit_to(&events, 1, 1)), (8.0, 21.0));
+        assert_eq!(grid.fit_to(&events, 0, 1).slot(), SlotLength::Min30);
+    }
+
+    #[test]
+    fn fitting_rounds_to_full_hours() {
+        let grid = TimeGrid::hours(8, 18, SlotLength::Min15);
+        let fitted = grid.fit_to(&[event(9.25, 10.75)], 0, 1);
+        assert_eq!(window(fitted), (9.0, 11.0));
+        assert_eq!(fitted.rows(), 8);
+    }
+
+    #[test]
+    fn fitting_keeps_min_hours() {
+        let grid = TimeGrid::hours(8, 18, SlotLength::Min60);
+        // Grown around the event
+        assert_eq!(
+            window(grid.fit_to(&[event(12.0, 13.0)], 0, 6)),
+            (10.0, 16.0)
+        );
+        // Grown only downwards at the end of the day
+        assert_eq!(
+            window(grid.fit_to(&[event(22.0, 23.5)], 0, 6)),
+            (18.0, 24.0)
+        );
+        // And only upwards at its start
+        assert_eq!(window(grid.fit_to(&[event(0.5, 1.0)], 0, 6)), (0.0, 6.0));
+        // Padding counts towards it
+        assert_eq!(
+            window(grid.fit_to(&[event(12.0, 13.0)], 2, 4)),
+            (10.0, 15.0)
+        );
+    }
+
+    #[test]
+    fn fitting_clamps_to_the_day() {
+        let grid = TimeGrid::hours(8, 18, SlotLength::Min60);
+        assert_eq!(window(grid.fit_to(&[event(-2.0, 1.0)], 2, 1)), (0.0, 3.0));
+        assert_eq!(
+            window(grid.fit_to(&[event(23.0, 26.0)], 2, 1)),
+            (21.0, 24.0)
+        );
+        assert_eq!(
+            window(grid.fit_to(&[event(12.0, 13.0)], 30, 1)),
+            (0.0, 24.0)
+        );
+        assert_eq!(
+            window(grid.fit_to(&[event(12.0, 13.0)], 0, 30)),
+            (0.0, 24.0)
+        );
+    }
+
+    #[test]
+    fn places_times_on_rows() {
+        let grid = TimeGrid::hours(7, 19, SlotLength::Min30);
+        assert_eq!(grid.row_offset(7.0), 0.0);
+        assert_eq!(grid.row_offset(8.25), 2.5);
+        assert_eq!(grid.row_offset(19.0), 24.0);
+        assert_eq!(grid.start_hour(), 7.0);
+        assert_eq!(grid.end_hour(), 19.0);
+    }
+}
@@ -0,0 +1,334 @@
+use serde::Deserialize;
+
+use crate::event::parse_date;
+use crate::schedule_table::Column;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    #[default]
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Days since Monday.
+    fn index(self) -> i64 {
+        self as i64
+    }
+
+    pub fn is_weekend(self) -> bool {
+        matches!(self, Weekday::Saturday | Weekday::Sunday)
+    }
+
+    /// Day of the week of `days` since 1970-01-01, a Thursday.
+    fn of(days: i64) -> Weekday {
+        Weekday::ALL[(days + 3).rem_euclid(7) as usize]
+    }
+}
+
+/// Days a [`Week`] shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "u32")]
+pub enum WeekLength {
+    /// The working days, Saturday and Sunday left out whatever day the week
+    /// starts on.
+    Five,
+    /// Every day, the weekend included.
+    #[default]
+    Seven,
+}
+
+impl WeekLength {
+    pub fn days(self) -> i64 {
+        match self {
+            WeekLength::Five => 5,
+            WeekLength::Seven => 7,
+        }
+    }
+}
+
+/// Days: 5 or 7.
+impl TryFrom<u32> for WeekLength {
+    type Error = String;
+
+    fn try_from(days: u32) -> Result<Self, Self::Error> {
+        match days {
+            5 => Ok(WeekLength::Five),
+            7 => Ok(WeekLength::Seven),
+            other => Err(format!("week of {other} days, expected 5 or 7")),
+        }
+    }
+}
+
+/// How day headers are written, for other languages and date orders. In
+/// `pattern`, `{day}` is replaced by the day's name, `{dd}`, `{mm}` and
+/// `{yyyy}` by its date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayFormat<'a> {
+    /// Monday first.
+    pub names: [&'a str; 7],
+    pub pattern: &'a str,
+}
+
+impl DayFormat<'_> {
+    /// `Mon 13.10`
+    pub const ENGLISH: DayFormat<'static> = DayFormat {
+        names: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        pattern: "{day} {dd}.{mm}",
+    };
+
+    fn format(&self, days: i64) -> String {
+        let (year, month, day) = civil_from_days(days);
+        self.pattern
+            .replace("{day}", self.names[Weekday::of(days).index() as usize])
+            .replace("{dd}", &format!("{day:02}"))
+            .replace("{mm}", &format!("{month:02}"))
+            .replace("{yyyy}", &year.to_string())
+    }
+}
+
+impl Default for DayFormat<'_> {
+    fn default() -> Self {
+        DayFormat::ENGLISH
+    }
+}
+
+/// Date columns of one week, for
+/// [`ScheduleTableBuilder::columns`](crate::schedule_table::ScheduleTableBuilder::columns).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Week<'a> {
+    /// First day, in days since 1970-01-01.
+    first: i64,
+    length: WeekLength,
+    format: DayFormat<'a>,
+    collapse_weekend: bool,
+}
+
+impl Week<'static> {
+    /// The seven days around `date`, starting on `first_day`. `None` if
+    /// `date` isn't a valid `dd.mm.yyyy` date.
+    pub fn containing(date: &str, first_day: Weekday) -> Option<Self> {
+        let (year, month, day) = parse_date(date)?;
+        let days = days_from_civil(year.into(), month.into(), day.into());
+        // 31.02. would silently become 03.03.
+        if civil_from_days(days) != (year.into(), month.into(), day.into()) {
+            return None;
+        }
+        let since_first = (Weekday::of(days).index() - first_day.index()).rem_euclid(7);
+        Some(Week {
+            first: days - since_first,
+            length: WeekLength::Seven,
+            format: DayFormat::ENGLISH,
+            collapse_weekend: false,
+        })
+    }
+}
+
+impl<'a> Week<'a> {
+    pub fn with_length(mut self, length: WeekLength) -> Self {
+        self.length = length;
+        self
+    }
+
+    pub fn with_format<'b>(self, format: DayFormat<'b>) -> Week<'b> {
+        Week {
+            first: self.first,
+            length: self.length,
+            format,
+            collapse_weekend: self.collapse_weekend,
+        }
+    }
+
+    /// Saturday and Sunday get a thin column when they have no events,
+    /// leaving the room to the working days.
+    pub fn with_collapsed_weekend(mut self, collapse: bool) -> Self {
+        self.collapse_weekend = collapse;
+        self
+    }
+
+    /// The days shown, in the format of [`Event::date`](crate::event::Event::date).
+    pub fn dates(&self) -> Vec<String> {
+        self.days()
+            .map(|days| {
+                let (year, month, day) = civil_from_days(days);
+                format!("{day:02}.{month:02}.{year}")
+            })
+            .collect()
+    }
+
+    /// One column per day, headed by [`DayFormat`].
+    pub fn columns(&self) -> Vec<Column<'static>> {
+        self.days()
+            .zip(self.dates())
+            .map(|(days, date)| {
+                let column = Column::date(date).with_header(self.format.format(days));
+                if self.collapse_weekend && Weekday::of(days).is_weekend() {
+                    column.collapsed_when_empty()
+                } else {
+                    column
+                }
+            })
+            .collect()
+    }
+
+    fn days(&self) -> impl Iterator<Item = i64> {
+        let length = self.length;
+        (self.first..self.first + 7)
+            .filter(move |&days| length == WeekLength::Seven || !Weekday::of(days).is_weekend())
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Years start in March, so the leap day is the last day of a year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// `(year, month, day)` of `days` since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(week: &Week<'_>) -> Vec<String> {
+        week.columns()
+            .iter()
+            .map(|column| column.header().to_string())
+            .collect()
+    }
+
+    fn collapsible(week: &Week<'_>) -> Vec<bool> {
+        week.columns()
+            .iter()
+            .map(|column| column.collapsible)
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_week_around_a_date() {
+        // 15.10.2025 is a Wednesday
+        let week = Week::containing("15.10.2025", Weekday::Monday).unwrap();
+        assert_eq!(week.dates().first().unwrap(), "13.10.2025");
+        assert_eq!(week.dates().last().unwrap(), "19.10.2025");
+        assert_eq!(headers(&week)[0], "Mon 13.10");
+
+        let week = Week::containing("15.10.2025", Weekday::Sunday).unwrap();
+        assert_eq!(week.dates().first().unwrap(), "12.10.2025");
+        assert_eq!(headers(&week)[0], "Sun 12.10");
+
+        assert_eq!(Week::containing("31.02.2025", Weekday::Monday), None);
+        assert_eq!(Week::containing("2025-10-15", Weekday::Monday), None);
+    }
+
+    #[test]
+    fn collapses_the_real_weekend() {
+        let week = Week::containing("15.10.2025", Weekday::Monday)
+            .unwrap()
+            .with_collapsed_weekend(true);
+        assert_eq!(
+            collapsible(&week),
+            [false, false, false, false, false, true, true]
+        );
+
+        // Starting on Sunday, the weekend is the first and the last day
+        let week = Week::containing("15.10.2025", Weekday::Sunday)
+            .unwrap()
+            .with_collapsed_weekend(true);
+        assert_eq!(
+            collapsible(&week),
+            [true, false, false, false, false, false, true]
+        );
+
+        let week = week.with_collapsed_weekend(false);
+        assert!(collapsible(&week).iter().all(|&collapsible| !collapsible));
+    }
+
+    #[test]
+    fn five_days_are_monday_to_friday() {
+        for first_day in [Weekday::Saturday, Weekday::Sunday, Weekday::Monday] {
+            let week = Week::containing("15.10.2025", first_day)
+                .unwrap()
+                .with_length(WeekLength::Five)
+                .with_collapsed_weekend(true);
+            let names: Vec<String> = headers(&week)
+                .iter()
+                .map(|header| header[..3].to_string())
+                .collect();
+            assert_eq!(names, ["Mon", "Tue", "Wed", "Thu", "Fri"], "{first_day:?}");
+            assert!(collapsible(&week).iter().all(|&collapsible| !collapsible));
+        }
+
+        // A week from Wednesday to Tuesday keeps its days in order
+        let week = Week::containing("15.10.2025", Weekday::Wednesday)
+            .unwrap()
+            .with_length(WeekLength::Five);
+        assert_eq!(
+            week.dates(),
+            [
+                "15.10.2025",
+                "16.10.2025",
+                "17.10.2025",
+                "20.10.2025",
+                "21.10.2025"
+            ]
+        );
+
+        // From Sunday the working days are those after it
+        let week = Week::containing("15.10.2025", Weekday::Sunday)
+            .unwrap()
+            .with_length(WeekLength::Five);
+        assert_eq!(week.dates().first().unwrap(), "13.10.2025");
+        assert_eq!(week.dates().last().unwrap(), "17.10.2025");
+    }
+
+    #[test]
+    fn formats_headers() {
+        let format = DayFormat {
+            names: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+            pattern: "{day}, {dd}.{mm}.{yyyy}",
+        };
+        let week = Week::containing("29.02.2024", Weekday::Monday)
+            .unwrap()
+            .with_format(format);
+        assert_eq!(headers(&week)[3], "Do, 29.02.2024");
+        assert_eq!(headers(&week)[6], "So, 03.03.2024");
+    }
+}
@@ -0,0 +1,442 @@
+//! Declarative board descriptions: which GPIO drives what, and which panel
+//! is fitted. Validated before any pin is touched.
+
+use std::fmt;
+
+/// Supported Waveshare panels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelKind {
+    /// 7.5" 800x480 black/white/red, B v3.
+    Epd7in5bV3,
+    /// 4.2" 400x300 black/white.
+    Epd4in2,
+    /// 2.9" 128x296 black/white, v2 (the Wokwi part).
+    Epd2in9V2,
+}
+
+impl PanelKind {
+    /// Level of the BUSY line while the controller is busy.
+    pub fn busy_active_low(self) -> bool {
+        match self {
+            PanelKind::Epd7in5bV3 | PanelKind::Epd4in2 => true,
+            PanelKind::Epd2in9V2 => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiPins {
+    pub sclk: u8,
+    pub mosi: u8,
+    pub cs: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardProfile {
+    pub name: &'static str,
+    pub panel: PanelKind,
+    pub spi: SpiPins,
+    pub busy: u8,
+    /// BUSY reads low while the controller is busy, must match the panel.
+    pub busy_active_low: bool,
+    pub dc: u8,
+    pub rst: u8,
+    /// Switches the panel supply, if the board has one.
+    pub power_enable: Option<u8>,
+    pub battery_adc: Option<u8>,
+    pub buttons: &'static [u8],
+    /// Strapping pins this board deliberately uses as outputs, because its
+    /// circuit keeps them at the right level during reset.
+    pub strapping_outputs_ok: &'static [u8],
+}
+
+/// Highest GPIO number on the ESP32.
+pub const MAX_GPIO: u8 = 39;
+/// GPIO34..=39 have no output driver.
+pub const INPUT_ONLY: [u8; 6] = [34, 35, 36, 37, 38, 39];
+/// Sampled at reset to select boot mode, flash voltage and timing.
+pub const STRAPPING: [u8; 5] = [0, 2, 5, 12, 15];
+/// Wired to the SPI flash on WROOM/WROVER modules.
+pub const FLASH: [u8; 6] = [6, 7, 8, 9, 10, 11];
+/// GPIOs usable as ADC inputs while Wi-Fi is running (ADC1).
+pub const ADC1: [u8; 8] = [32, 33, 34, 35, 36, 37, 38, 39];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinIssue {
+    DoesNotExist {
+        role: &'static str,
+        gpio: u8,
+    },
+    Reserved {
+        role: &'static str,
+        gpio: u8,
+    },
+    AssignedTwice {
+        gpio: u8,
+        first: &'static str,
+        second: &'static str,
+    },
+    InputOnlyAsOutput {
+        role: &'static str,
+        gpio: u8,
+    },
+    StrappingAsOutput {
+        role: &'static str,
+        gpio: u8,
+    },
+    NotAdc1 {
+        role: &'static str,
+        gpio: u8,
+    },
+    BusyPolarity {
+        expected_active_low: bool,
+    },
+}
+
+impl fmt::Display for PinIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinIssue::DoesNotExist { role, gpio } => write!(f, "{role}: GPIO{gpio} does not exist"),
+            PinIssue::Reserved { role, gpio } => {
+                write!(f, "{role}: GPIO{gpio} is wired to the SPI flash")
+            }
+            PinIssue::AssignedTwice {
+                gpio,
+                first,
+                second,
+            } => write!(f, "GPIO{gpio} is used for both {first} and {second}"),
+            PinIssue::InputOnlyAsOutput { role, gpio } => {
+                write!(f, "{role}: GPIO{gpio} is input-only")
+            }
+            PinIssue::StrappingAsOutput { role, gpio } => {
+                write!(f, "{role}: GPIO{gpio} is a strapping pin")
+            }
+            PinIssue::NotAdc1 { role, gpio } => {
+                write!(f, "{role}: GPIO{gpio} is not an ADC1 channel")
+            }
+            PinIssue::BusyPolarity {
+                expected_active_low,
+            } => write!(
+                f,
+                "busy polarity does not match the panel (expected active {})",
+                if *expected_active_low { "low" } else { "high" }
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBoard {
+    pub board: &'static str,
+    pub issues: Vec<PinIssue>,
+}
+
+impl fmt::Display for InvalidBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "board '{}' is misconfigured:", self.board)?;
+        for issue in &self.issues {
+            write!(f, " {issue};")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidBoard {}
+
+impl BoardProfile {
+    /// Every pin with its role, `true` when the firmware drives it.
+    pub fn assignments(&self) -> Vec<(&'static str, u8, bool)> {
+        let mut pins = vec![
+            ("spi sclk", self.spi.sclk, true),
+            ("spi mosi", self.spi.mosi, true),
+            ("spi cs", self.spi.cs, true),
+            ("busy", self.busy, false),
+            ("dc", self.dc, true),
+            ("rst", self.rst, true),
+        ];
+        if let Some(gpio) = self.power_enable {
+            pins.push(("power enable", gpio, true));
+        }
+        if let Some(gpio) = self.battery_adc {
+            pins.push(("battery adc", gpio, false));
+        }
+        pins.extend(self.buttons.iter().map(|&gpio| ("button", gpio, false)));
+        pins
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidBoard> {
+        let mut issues = Vec::new();
+        let pins = self.assignments();
+
+        for (i, &(role, gpio, output)) in pins.iter().enumerate() {
+            if gpio > MAX_GPIO || matches!(gpio, 20 | 24 | 28..=31) {
+                issues.push(PinIssue::DoesNotExist { role, gpio });
+                continue;
+            }
+            if FLASH.contains(&gpio) {
+                issues.push(PinIssue::Reserved { role, gpio });
+            }
+            if let Some(&(first, ..)) = pins[..i].iter().find(|(_, other, _)| *other == gpio) {
+                issues.push(PinIssue::AssignedTwice {
+                    gpio,
+                    first,
+                    second: role,
+                });
+            }
+            if output && INPUT_ONLY.contains(&gpio) {
+                issues.push(PinIssue::InputOnlyAsOutput { role, gpio });
+            }
+            if output && STRAPPING.contains(&gpio) && !self.strapping_outputs_ok.contains(&gpio) {
+                issues.push(PinIssue::StrappingAsOutput { role, gpio });
+            }
+        }
+
+        if let Some(gpio) = self.battery_adc {
+            if !ADC1.contains(&gpio) {
+                issues.push(PinIssue::NotAdc1 {
+                    role: "battery adc",
+                    gpio,
+                });
+            }
+        }
+
+        let expected_active_low = self.panel.busy_active_low();
+        if self.busy_active_low != expected_active_low {
+            issues.push(PinIssue::BusyPolarity {
+                expected_active_low,
+            });
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidBoard {
+                board: self.name,
+                issues,
+            })
+        }
+    }
+}
+
+/// Waveshare "e-Paper ESP32 Driver Board" with the 7.5" B panel.
+pub const WAVESHARE_DRIVER_BOARD: BoardProfile = BoardProfile {
+    name: "waveshare-esp32-driver",
+    panel: PanelKind::Epd7in5bV3,
+    spi: SpiPins {
+        sclk: 13,
+        mosi: 14,
+        cs: 15,
+    },
+    busy: 25,
+    busy_active_low: true,
+    dc: 27,
+    rst: 26,
+    power_enable: None,
+    battery_adc: None,
+    buttons: &[],
+    strapping_outputs_ok: &[15],
+};
+
+/// LilyGo T5 V2.3 with the 2.9" panel.
+pub const LILYGO_T5: BoardProfile = BoardProfile {
+    name: "lilygo-t5",
+    panel: PanelKind::Epd2in9V2,
+    spi: SpiPins {
+        sclk: 18,
+        mosi: 23,
+        cs: 5,
+    },
+    busy: 4,
+    busy_active_low: false,
+    dc: 17,
+    rst: 16,
+    power_enable: None,
+    battery_adc: Some(35),
+    buttons: &[39],
+    strapping_outputs_ok: &[5],
+};
+
+/// Our own carrier PCB for the 7.5" B panel with a switched panel supply.
+pub const CUSTOM_PCB: BoardProfile = BoardProfile {
+    name: "custom-pcb",
+    panel: PanelKind::Epd7in5bV3,
+    spi: SpiPins {
+        sclk: 18,
+        mosi: 23,
+        cs: 5,
+    },
+    busy: 4,
+    busy_active_low: true,
+    dc: 17,
+    rst: 16,
+    power_enable: Some(2),
+    battery_adc: None,
+    buttons: &[],
+    strapping_outputs_ok: &[2, 5],
+};
+
+/// Wiring in `diagram.json` for the Wokwi simulator.
+pub const WOKWI: BoardProfile = BoardProfile {
+    name: "wokwi",
+    panel: PanelKind::Epd2in9V2,
+    spi: SpiPins {
+        sclk: 18,
+        mosi: 19,
+        cs: 5,
+    },
+    busy: 4,
+    busy_active_low: false,
+    dc: 23,
+    rst: 21,
+    power_enable: Some(2),
+    battery_adc: None,
+    buttons: &[],
+    strapping_outputs_ok: &[2, 5],
+};
+
+pub const ALL: [&BoardProfile; 4] = [&WAVESHARE_DRIVER_BOARD, &LILYGO_T5, &CUSTOM_PCB, &WOKWI];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(board: BoardProfile) -> Vec<PinIssue> {
+        board
+            .validate()
+            .map_or_else(|err| err.issues, |()| Vec::new())
+    }
+
+    #[test]
+    fn built_in_profiles_are_valid() {
+        for board in ALL {
+            assert_eq!(board.validate(), Ok(()), "{}", board.name);
+        }
+    }
+
+    #[test]
+    fn rejects_pin_used_twice() {
+        let board = BoardProfile {
+            dc: CUSTOM_PCB.busy,
+            ..CUSTOM_PCB
+        };
+        assert_eq!(
+            issues(board),
+            [PinIssue::AssignedTwice {
+                gpio: 4,
+                first: "busy",
+                second: "dc",
+            }]
+        );
+
+        let board = BoardProfile {
+            buttons: &[0, 0],
+            ..CUSTOM_PCB
+        };
+        assert_eq!(
+            issues(board),
+            [PinIssue::AssignedTwice {
+                gpio: 0,
+                first: "button",
+                second: "button",
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_input_only_output() {
+        let board = BoardProfile {
+            rst: 34,
+            power_enable: Some(39),
+            ..CUSTOM_PCB
+        };
+        assert_eq!(
+            issues(board),
+            [
+                PinIssue::InputOnlyAsOutput {
+                    role: "rst",
+                    gpio: 34,
+                },
+                PinIssue::InputOnlyAsOutput {
+                    role: "power enable",
+                    gpio: 39,
+                },
+            ]
+        );
+        // Fine as inputs
+        let board = BoardProfile {
+            busy: 36,
+            buttons: &[34],
+            ..CUSTOM_PCB
+        };
+        assert_eq!(board.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_mismatched_busy_polarity() {
+        let board = BoardProfile {
+            busy_active_low: true,
+            ..LILYGO_T5
+        };
+        assert_eq!(
+            issues(board),
+            [PinIssue::BusyPolarity {
+                expected_active_low: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_unusable_pins() {
+        let board = BoardProfile {
+            spi: SpiPins {
+                sclk: 6,
+                mosi: 40,
+                cs: 20,
+            },
+            dc: 12,
+            battery_adc: Some(25),
+            ..CUSTOM_PCB
+        };
+        assert_eq!(
+            issues(board),
+            [
+                PinIssue::Reserved {
+                    role: "spi sclk",
+                    gpio: 6,
+                },
+                PinIssue::DoesNotExist {
+                    role: "spi mosi",
+                    gpio: 40,
+                },
+                PinIssue::DoesNotExist {
+                    role: "spi cs",
+                    gpio: 20,
+                },
+                PinIssue::StrappingAsOutput {
+                    role: "dc",
+                    gpio: 12
+                },
+                PinIssue::NotAdc1 {
+                    role: "battery adc",
+                    gpio: 25,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_every_issue() {
+        let err = BoardProfile {
+            rst: 34,
+            busy_active_low: false,
+            ..CUSTOM_PCB
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "board 'custom-pcb' is misconfigured: rst: GPIO34 is input-only; \
+             busy polarity does not match the panel (expected active low);"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+
+use crate::http::{RequestSpec, Response};
+
+/// Cache validators the server sent with the last schedule we rendered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_response(response: &Response) -> Self {
+        Validators {
+            etag: response.header("etag").map(str::to_string),
+            last_modified: response.header("last-modified").map(str::to_string),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Turns `request` into a conditional GET.
+    pub fn apply(&self, request: &mut RequestSpec) {
+        if let Some(etag) = &self.etag {
+            request
+                .headers
+                .push(("If-None-Match".to_string(), etag.clone()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request
+                .headers
+                .push(("If-Modified-Since".to_string(), last_modified.clone()));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchOutcome {
+    /// New schedule; persist `validators` once it has been rendered.
+    Updated {
+        body: Vec<u8>,
+        validators: Validators,
+    },
+    /// `304 Not Modified`, the panel already shows the current schedule.
+    NotModified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchError {
+    Status(u16),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Status(status) => write!(f, "server responded with status {status}"),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Maps the final (non-redirect) response onto what the device should do.
+pub fn classify(response: Response) -> Result<FetchOutcome, FetchError> {
+    match response.status {
+        304 => Ok(FetchOutcome::NotModified),
+        200..=299 => Ok(FetchOutcome::Updated {
+            validators: Validators::from_response(&response),
+            body: response.body,
+        }),
+        status => Err(FetchError::Status(status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::HostTransport;
+    use crate::http::ServerConfig;
+    use crate::test_server::{TestServer, header};
+    use crate::transport::fetch_schedule;
+
+    const ETAG: &str = "\"v1\"";
+    const LAST_MODIFIED: &str = "Wed, 01 Jan 2025 08:00:00 GMT";
+
+    /// What the device keeps between wakes: the validators and the schedule
+    /// of the frame on the panel.
+    #[derive(Debug, Default, PartialEq)]
+    struct Shown {
+        validators: Validators,
+        body: Vec<u8>,
+    }
+
+    impl Shown {
+        /// One wake against a server answering `response`, returning the
+        /// request it sent.
+        fn wake(&mut self, response: &str) -> (Result<(), std::io::Error>, String) {
+            let server = TestServer::start(&[response]);
+            let config = ServerConfig::new(&server.url("/schedule"))
+                .unwrap()
+                .allow_insecure(true);
+            let outcome = fetch_schedule(&mut HostTransport::default(), &config, &self.validators);
+            let result = outcome.map(|outcome| match outcome {
+                FetchOutcome::Updated { body, validators } => {
+                    *self = Shown { validators, body };
+                }
+                FetchOutcome::NotModified => {}
+            });
+            (result, server.requests().remove(0))
+        }
+    }
+
+    #[test]
+    fn conditional_get_transitions() {
+        let mut shown = Shown::default();
+
+        let (result, request) = shown.wake(&format!(
+            "HTTP/1.1 200 OK\r\nETag: {ETAG}\r\nLast-Modified: {LAST_MODIFIED}\r\n\
+             Content-Length: 2\r\n\r\n[]"
+        ));
+        assert!(result.is_ok());
+        assert_eq!(header(&request, "if-none-match"), None);
+        assert_eq!(header(&request, "if-modified-since"), None);
+        let updated = Shown {
+            validators: Validators {
+                etag: Some(ETAG.to_string()),
+                last_modified: Some(LAST_MODIFIED.to_string()),
+            },
+            body: b"[]".to_vec(),
+        };
+        assert_eq!(shown, updated);
+
+        // Not modified: sends the validators, keeps what is shown
+        let (result, request) = shown.wake("HTTP/1.1 304 Not Modified\r\n\r\n");
+        assert!(result.is_ok());
+        assert_eq!(header(&request, "if-none-match"), Some(ETAG));
+        assert_eq!(header(&request, "if-modified-since"), Some(LAST_MODIFIED));
+        assert_eq!(shown, updated);
+
+        // Errors leave everything as it was
+        for response in [
+            "HTTP/1.1 404 Not Found\r\nETag: \"gone\"\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\n\r\nbusy",
+        ] {
+            let (result, request) = shown.wake(response);
+            assert!(result.is_err());
+            assert_eq!(header(&request, "if-none-match"), Some(ETAG));
+            assert_eq!(shown, updated);
+        }
+
+        // A changed schedule replaces both, missing validators are dropped
+        let (result, _) =
+            shown.wake("HTTP/1.1 200 OK\r\nETag: \"v2\"\r\nContent-Length: 4\r\n\r\n[{}]");
+        assert!(result.is_ok());
+        assert_eq!(shown.validators.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(shown.validators.last_modified, None);
+        assert_eq!(shown.body, b"[{}]");
+    }
+
+    #[test]
+    fn classifies_statuses() {
+        let response = |status| Response {
+            status,
+            headers: vec![("etag".to_string(), ETAG.to_string())],
+            body: b"[]".to_vec(),
+        };
+        assert_eq!(classify(response(304)), Ok(FetchOutcome::NotModified));
+        assert_eq!(
+            classify(response(200)),
+            Ok(FetchOutcome::Updated {
+                body: b"[]".to_vec(),
+                validators: Validators {
+                    etag: Some(ETAG.to_string()),
+                    last_modified: None,
+                },
+            })
+        );
+        assert_eq!(classify(response(401)), Err(FetchError::Status(401)));
+        assert_eq!(classify(response(500)), Err(FetchError::Status(500)));
+    }
+}
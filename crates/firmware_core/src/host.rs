@@ -0,0 +1,234 @@
+//! Plain `std::net` HTTP/1.1 transport used to run the request logic against
+//! a local test server on Linux. Only `http://` urls are supported.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::cache::FetchError;
+use crate::http::{DEFAULT_TIMEOUT, HttpError, Method, RequestSpec, Response, Scheme};
+use crate::transport::ScheduleTransport;
+
+#[derive(Debug, Clone)]
+pub struct HostTransport {
+    pub timeout: Duration,
+}
+
+impl Default for HostTransport {
+    fn default() -> Self {
+        HostTransport {
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl ScheduleTransport for HostTransport {
+    type Error = io::Error;
+
+    fn send(&mut self, request: &RequestSpec, body: &[u8]) -> io::Result<Response> {
+        if request.url.scheme != Scheme::Http {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "host transport only speaks plaintext http",
+            ));
+        }
+
+        let mut stream = TcpStream::connect((request.url.host.as_str(), request.url.port))?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        let method = match request.method {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+        };
+        let mut head = format!(
+            "{method} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+            request.url.path,
+            request.url.authority()
+        );
+        for (name, value) in &request.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if !body.is_empty() || request.method == Method::Post {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+
+        read_response(BufReader::new(stream), request.method == Method::Head)
+    }
+}
+
+impl From<HttpError> for io::Error {
+    fn from(err: HttpError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl From<FetchError> for io::Error {
+    fn from(err: FetchError) -> Self {
+        io::Error::other(err)
+    }
+}
+
+fn read_response(mut reader: impl BufRead, head_only: bool) -> io::Result<Response> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid_data("malformed status line"))?;
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = trimmed.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut response = Response {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+    if head_only || status == 204 || status == 304 {
+        return Ok(response);
+    }
+
+    if response
+        .header("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    {
+        response.body = read_chunked(&mut reader)?;
+    } else if let Some(len) = response.header("content-length") {
+        let len: usize = len
+            .parse()
+            .map_err(|_| invalid_data("bad content-length"))?;
+        response.body = vec![0; len];
+        reader.read_exact(&mut response.body)?;
+    } else {
+        reader.read_to_end(&mut response.body)?;
+    }
+
+    Ok(response)
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid_data("bad chunk size"))?;
+        if size == 0 {
+            return Ok(body);
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Validators;
+    use crate::http::{ServerConfig, Url};
+    use crate::test_server::{TestServer, header};
+    use crate::transport::fetch_schedule;
+
+    #[test]
+    fn host_header_carries_non_default_port() {
+        let server = TestServer::start(&["HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"]);
+        let url = Url::parse(&server.url("/schedule?room=1")).unwrap();
+
+        let response = HostTransport::default().get(&url, &[]).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ok");
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("GET /schedule?room=1 HTTP/1.1\r\n"));
+        assert_eq!(
+            header(&requests[0], "host"),
+            Some(format!("127.0.0.1:{}", url.port).as_str())
+        );
+    }
+
+    #[test]
+    fn reads_chunked_body() {
+        let server = TestServer::start(&[
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nsche\r\n4;x=y\r\ndule\r\n0\r\n\r\n",
+        ]);
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        let response = HostTransport::default().get(&url, &[]).unwrap();
+        assert_eq!(response.body, b"schedule");
+    }
+
+    #[test]
+    fn posts_body_with_length() {
+        let server = TestServer::start(&["HTTP/1.1 204 No Content\r\n\r\n"]);
+        let url = Url::parse(&server.url("/telemetry")).unwrap();
+
+        let response = HostTransport::default()
+            .post(&url, &[], b"{\"battery\":19}")
+            .unwrap();
+        assert_eq!(response.status, 204);
+
+        let requests = server.requests();
+        assert_eq!(header(&requests[0], "content-length"), Some("14"));
+        assert!(requests[0].ends_with("\r\n\r\n{\"battery\":19}"));
+    }
+
+    #[test]
+    fn token_stays_with_its_origin_across_redirects() {
+        let elsewhere = TestServer::start(&["HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n[]"]);
+        let moved = format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: {}\r\n\r\n",
+            elsewhere.url("/schedule")
+        );
+        let origin = TestServer::start(&["HTTP/1.1 302 Found\r\nLocation: /moved\r\n\r\n", &moved]);
+        let config = ServerConfig::new(&origin.url("/schedule"))
+            .unwrap()
+            .allow_insecure(true)
+            .with_auth_token("secret");
+
+        let outcome = fetch_schedule(
+            &mut HostTransport::default(),
+            &config,
+            &Validators::default(),
+        );
+        assert!(outcome.is_ok());
+
+        let requests = origin.requests();
+        assert!(requests[1].starts_with("GET /moved "));
+        assert_eq!(header(&requests[0], "authorization"), Some("Bearer secret"));
+        assert_eq!(header(&requests[1], "authorization"), Some("Bearer secret"));
+        assert_eq!(header(&elsewhere.requests()[0], "authorization"), None);
+    }
+
+    #[test]
+    fn refuses_https() {
+        let url = Url::parse("https://schedule.example.com/").unwrap();
+        let err = HostTransport::default().get(&url, &[]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
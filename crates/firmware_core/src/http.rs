@@ -0,0 +1,377 @@
+use std::fmt;
+use std::time::Duration;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_MAX_REDIRECTS: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Http,
+    Https,
+}
+
+impl Scheme {
+    pub fn default_port(self) -> u16 {
+        match self {
+            Scheme::Http => 80,
+            Scheme::Https => 443,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpError {
+    InvalidUrl(String),
+    InsecureScheme(String),
+    TooManyRedirects(u8),
+    MissingLocation(u16),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::InvalidUrl(url) => write!(f, "invalid url: {url}"),
+            HttpError::InsecureScheme(url) => write!(f, "plaintext url not allowed: {url}"),
+            HttpError::TooManyRedirects(max) => write!(f, "more than {max} redirects"),
+            HttpError::MissingLocation(status) => {
+                write!(f, "redirect status {status} without Location header")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Absolute `http(s)://host[:port]/path?query` url.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    pub scheme: Scheme,
+    pub host: String,
+    pub port: u16,
+    /// Path including the query string, always starts with `/`.
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Self, HttpError> {
+        let invalid = || HttpError::InvalidUrl(url.to_string());
+
+        let (scheme, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (Scheme::Https, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (Scheme::Http, rest)
+        } else {
+            return Err(invalid());
+        };
+
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, scheme.default_port()),
+        };
+
+        if host.is_empty() || host.contains(['@', ' ']) {
+            return Err(invalid());
+        }
+
+        Ok(Url {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port,
+            path,
+        })
+    }
+
+    /// Resolves a `Location` header value against this url.
+    pub fn join(&self, location: &str) -> Result<Self, HttpError> {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            return Url::parse(location);
+        }
+        if let Some(rest) = location.strip_prefix("//") {
+            let scheme = match self.scheme {
+                Scheme::Http => "http",
+                Scheme::Https => "https",
+            };
+            return Url::parse(&format!("{scheme}://{rest}"));
+        }
+
+        let path = if location.starts_with('/') {
+            location.to_string()
+        } else {
+            let base = self.path.split('?').next().unwrap_or("/");
+            let dir = &base[..=base.rfind('/').unwrap_or(0)];
+            format!("{dir}{location}")
+        };
+
+        Ok(Url {
+            path,
+            ..self.clone()
+        })
+    }
+
+    /// `host`, with `:port` unless it is the scheme's default, as sent in
+    /// the `Host` header.
+    pub fn authority(&self) -> String {
+        if self.port == self.scheme.default_port() {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    /// Same scheme, host and port.
+    pub fn same_origin(&self, other: &Url) -> bool {
+        self.scheme == other.scheme && self.host == other.host && self.port == other.port
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.scheme {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        };
+        write!(f, "{scheme}://{}{}", self.authority(), self.path)
+    }
+}
+
+/// Where the TLS trust anchor for the schedule server comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaSource {
+    /// ESP-IDF certificate bundle (common public roots).
+    Bundle,
+    /// Single PEM certificate pinned in NVS.
+    Pinned(String),
+}
+
+/// Schedule server settings, stored in NVS on the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub url: Url,
+    pub auth_token: Option<String>,
+    pub ca: CaSource,
+    pub timeout: Duration,
+    pub max_redirects: u8,
+    /// Allow `http://` urls, only meant for local test servers.
+    pub allow_insecure: bool,
+}
+
+impl ServerConfig {
+    pub fn new(url: &str) -> Result<Self, HttpError> {
+        Ok(ServerConfig {
+            url: Url::parse(url)?,
+            auth_token: None,
+            ca: CaSource::Bundle,
+            timeout: DEFAULT_TIMEOUT,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            allow_insecure: false,
+        })
+    }
+
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    pub fn with_ca(mut self, ca: CaSource) -> Self {
+        self.ca = ca;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_redirects(mut self, max_redirects: u8) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    pub fn allow_insecure(mut self, allow: bool) -> Self {
+        self.allow_insecure = allow;
+        self
+    }
+
+    /// Request for the schedule document.
+    pub fn schedule_request(&self) -> Result<RequestSpec, HttpError> {
+        self.get("application/json")
+    }
+
+    /// Authenticated GET of the configured url.
+    pub fn get(&self, accept: &str) -> Result<RequestSpec, HttpError> {
+        self.check_scheme(&self.url)?;
+
+        let mut headers = vec![("Accept".to_string(), accept.to_string())];
+        if let Some(token) = &self.auth_token {
+            headers.push(("Authorization".to_string(), format!("Bearer {token}")));
+        }
+
+        Ok(RequestSpec {
+            method: Method::Get,
+            url: self.url.clone(),
+            headers,
+            redirects: 0,
+        })
+    }
+
+    /// Builds the follow-up request for a redirect response, or fails when
+    /// the redirect budget is used up.
+    pub fn follow_redirect(
+        &self,
+        previous: &RequestSpec,
+        status: u16,
+        location: Option<&str>,
+    ) -> Result<RequestSpec, HttpError> {
+        if previous.redirects >= self.max_redirects {
+            return Err(HttpError::TooManyRedirects(self.max_redirects));
+        }
+        let location = location.ok_or(HttpError::MissingLocation(status))?;
+        let url = previous.url.join(location)?;
+        self.check_scheme(&url)?;
+
+        // Never leak the bearer token to another origin.
+        let mut headers = previous.headers.clone();
+        if !url.same_origin(&previous.url) {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("authorization"));
+        }
+
+        // 303 always becomes a GET, 307/308 keep the method.
+        let method = match status {
+            303 => Method::Get,
+            _ => previous.method,
+        };
+
+        Ok(RequestSpec {
+            method,
+            url,
+            headers,
+            redirects: previous.redirects + 1,
+        })
+    }
+
+    fn check_scheme(&self, url: &Url) -> Result<(), HttpError> {
+        if url.scheme == Scheme::Http && !self.allow_insecure {
+            return Err(HttpError::InsecureScheme(url.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+}
+
+/// Transport independent description of a single request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestSpec {
+    pub method: Method,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    /// How many redirects were followed to get here.
+    pub redirects: u8,
+}
+
+impl RequestSpec {
+    pub fn new(method: Method, url: Url, headers: &[(String, String)]) -> Self {
+        RequestSpec {
+            method,
+            url,
+            headers: headers.to_vec(),
+            redirects: 0,
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub fn is_redirect(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_port_and_query() {
+        let url = Url::parse("https://Schedule.example.com:8443?room=1").unwrap();
+        assert_eq!(url.host, "schedule.example.com");
+        assert_eq!(url.port, 8443);
+        assert_eq!(url.path, "/?room=1");
+        assert_eq!(url.authority(), "schedule.example.com:8443");
+        assert_eq!(url.to_string(), "https://schedule.example.com:8443/?room=1");
+
+        let url = Url::parse("https://schedule.example.com:443/a").unwrap();
+        assert_eq!(url.authority(), "schedule.example.com");
+        assert!(Url::parse("https://user@host/").is_err());
+        assert!(Url::parse("https://host:port/").is_err());
+    }
+
+    #[test]
+    fn joins_locations() {
+        let base = Url::parse("https://example.com/api/v1/schedule?room=1").unwrap();
+        assert_eq!(base.join("other").unwrap().path, "/api/v1/other");
+        assert_eq!(base.join("/root").unwrap().path, "/root");
+        assert_eq!(
+            base.join("//cdn.example.com/x").unwrap().to_string(),
+            "https://cdn.example.com/x"
+        );
+        assert_eq!(
+            base.join("http://example.com/").unwrap().scheme,
+            Scheme::Http
+        );
+    }
+
+    #[test]
+    fn redirects_are_bounded_and_checked() {
+        let config = ServerConfig::new("https://example.com/schedule")
+            .unwrap()
+            .with_max_redirects(1);
+        let first = config.get("*/*").unwrap();
+
+        let second = config.follow_redirect(&first, 303, Some("/b")).unwrap();
+        assert_eq!(second.redirects, 1);
+        assert_eq!(
+            config.follow_redirect(&second, 302, Some("/c")),
+            Err(HttpError::TooManyRedirects(1))
+        );
+        assert_eq!(
+            config.follow_redirect(&first, 302, None),
+            Err(HttpError::MissingLocation(302))
+        );
+        assert!(matches!(
+            config.follow_redirect(&first, 302, Some("http://example.com/")),
+            Err(HttpError::InsecureScheme(_))
+        ));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod board;
+pub mod cache;
+pub mod host;
+pub mod http;
+pub mod ota;
+#[cfg(test)]
+mod test_server;
+pub mod transport;
+pub mod wifi;
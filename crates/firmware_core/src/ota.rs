@@ -0,0 +1,334 @@
+//! Firmware update manifest handling and image verification. Flashing itself
+//! is done by the firmware through ESP-IDF's OTA partitions.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OtaError {
+    InvalidVersion(String),
+    InvalidManifest(String),
+    TooLarge { size: u32, max: u32 },
+    SizeMismatch { expected: u32, actual: u32 },
+    DigestMismatch,
+}
+
+impl fmt::Display for OtaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OtaError::InvalidVersion(version) => write!(f, "invalid version '{version}'"),
+            OtaError::InvalidManifest(reason) => write!(f, "invalid update manifest: {reason}"),
+            OtaError::TooLarge { size, max } => {
+                write!(f, "image of {size} bytes does not fit the {max} byte slot")
+            }
+            OtaError::SizeMismatch { expected, actual } => {
+                write!(f, "expected {expected} image bytes, got {actual}")
+            }
+            OtaError::DigestMismatch => write!(f, "image SHA-256 does not match the manifest"),
+        }
+    }
+}
+
+impl std::error::Error for OtaError {}
+
+/// `major.minor.patch`, an optional leading `v` and any `-suffix` are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FromStr for Version {
+    type Err = OtaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || OtaError::InvalidVersion(s.to_string());
+        let core = s.trim().trim_start_matches('v');
+        let core = core.split(['-', '+']).next().unwrap_or_default();
+
+        let parts: Vec<&str> = core.split('.').collect();
+        if core.is_empty() || parts.len() > 3 {
+            return Err(err());
+        }
+
+        let part = |i: usize| parts.get(i).map_or(Ok(0), |p| p.parse().map_err(|_| err()));
+        Ok(Version {
+            major: part(0)?,
+            minor: part(1)?,
+            patch: part(2)?,
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawManifest {
+    version: String,
+    url: String,
+    sha256: String,
+    size: u32,
+}
+
+/// Update manifest published next to the firmware images.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: Version,
+    pub url: String,
+    pub sha256: [u8; 32],
+    pub size: u32,
+}
+
+impl Manifest {
+    /// Parses `{"version": "1.2.0", "url": "...", "sha256": "<hex>", "size": 123}`.
+    pub fn parse(json: &[u8]) -> Result<Self, OtaError> {
+        let raw: RawManifest = serde_json::from_slice(json)
+            .map_err(|err| OtaError::InvalidManifest(err.to_string()))?;
+
+        if raw.size == 0 {
+            return Err(OtaError::InvalidManifest("size is zero".to_string()));
+        }
+
+        Ok(Manifest {
+            version: raw.version.parse()?,
+            url: raw.url,
+            sha256: parse_sha256(&raw.sha256)?,
+            size: raw.size,
+        })
+    }
+
+    /// Whether this manifest offers something newer than `current` that fits
+    /// into an OTA slot of `slot_size` bytes.
+    pub fn is_update_for(&self, current: &Version, slot_size: u32) -> Result<bool, OtaError> {
+        if self.size > slot_size {
+            return Err(OtaError::TooLarge {
+                size: self.size,
+                max: slot_size,
+            });
+        }
+        Ok(self.version.cmp(current) == Ordering::Greater)
+    }
+}
+
+fn parse_sha256(hex: &str) -> Result<[u8; 32], OtaError> {
+    let err = || OtaError::InvalidManifest(format!("bad sha256 '{hex}'"));
+    let hex = hex.trim();
+    // from_str_radix alone would also take a sign
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(err());
+    }
+
+    let mut digest = [0; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| err())?;
+    }
+    Ok(digest)
+}
+
+/// Hashes the image while it streams into flash.
+pub struct ImageVerifier {
+    hasher: Sha256,
+    written: u32,
+    expected_size: u32,
+    expected_sha256: [u8; 32],
+}
+
+impl ImageVerifier {
+    pub fn new(manifest: &Manifest) -> Self {
+        ImageVerifier {
+            hasher: Sha256::new(),
+            written: 0,
+            expected_size: manifest.size,
+            expected_sha256: manifest.sha256,
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) -> Result<(), OtaError> {
+        let written = self.written.saturating_add(chunk.len() as u32);
+        if written > self.expected_size {
+            return Err(OtaError::SizeMismatch {
+                expected: self.expected_size,
+                actual: written,
+            });
+        }
+        self.written = written;
+        self.hasher.update(chunk);
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), OtaError> {
+        if self.written != self.expected_size {
+            return Err(OtaError::SizeMismatch {
+                expected: self.expected_size,
+                actual: self.written,
+            });
+        }
+        if self.hasher.finalize().as_slice() != self.expected_sha256 {
+            return Err(OtaError::DigestMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE: &[u8] = b"not really a firmware image";
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    fn manifest_json(version: &str, sha256: &str, size: u32) -> String {
+        format!(
+            r#"{{"version": "{version}", "url": "https://example.com/fw.bin", "sha256": "{sha256}", "size": {size}}}"#
+        )
+    }
+
+    fn manifest(version: &str) -> Manifest {
+        let sha256 = hex(&Sha256::digest(IMAGE));
+        Manifest::parse(manifest_json(version, &sha256, IMAGE.len() as u32).as_bytes()).unwrap()
+    }
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_valid_manifest() {
+        let sha256 = Sha256::digest(IMAGE);
+        let json = manifest_json("v1.2.3-rc1", &hex(&sha256).to_uppercase(), 27);
+        let manifest = Manifest::parse(json.as_bytes()).unwrap();
+        assert_eq!(manifest.version, version("1.2.3"));
+        assert_eq!(manifest.url, "https://example.com/fw.bin");
+        assert_eq!(manifest.sha256.as_slice(), sha256.as_slice());
+        assert_eq!(manifest.size, 27);
+    }
+
+    #[test]
+    fn rejects_malformed_manifests() {
+        let sha256 = "ab".repeat(32);
+        for json in [
+            String::new(),
+            "[]".to_string(),
+            r#"{"version": "1.0.0", "url": "https://example.com/fw.bin", "size": 1}"#.to_string(),
+            manifest_json("1.0.0", &sha256, 0),
+            manifest_json("1.0.0", &sha256, 1).replace("\"size\": 1", "\"size\": -1"),
+        ] {
+            assert!(
+                matches!(
+                    Manifest::parse(json.as_bytes()),
+                    Err(OtaError::InvalidManifest(_))
+                ),
+                "{json}"
+            );
+        }
+        assert_eq!(
+            Manifest::parse(manifest_json("one", &sha256, 1).as_bytes()),
+            Err(OtaError::InvalidVersion("one".to_string()))
+        );
+    }
+
+    #[test]
+    fn validates_sha256() {
+        let valid = "0123456789abcdef".repeat(4);
+        assert!(Manifest::parse(manifest_json("1.0.0", &valid, 1).as_bytes()).is_ok());
+        for sha256 in [
+            &valid[1..],
+            &format!("{valid}0"),
+            &valid.replacen('0', "g", 1),
+            &valid.replacen("01", "é", 1),
+            &valid.replacen("01", "+1", 1),
+            "",
+        ] {
+            let json = manifest_json("1.0.0", sha256, 1);
+            assert!(
+                matches!(
+                    Manifest::parse(json.as_bytes()),
+                    Err(OtaError::InvalidManifest(_))
+                ),
+                "{sha256}"
+            );
+        }
+    }
+
+    #[test]
+    fn orders_versions() {
+        let ordered = [
+            "0.0.9", "0.1", "0.1.1", "0.2.0", "0.10.0", "1", "1.0.1", "v1.9.0", "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{pair:?}");
+        }
+        assert_eq!(version("v1.2"), version("1.2.0"));
+        assert_eq!(version("1.2.3+build.7"), version("1.2.3"));
+        assert_eq!(version(" 1.2.3 ").to_string(), "1.2.3");
+        for bad in ["", "v", "1.2.3.4", "1..2", "1.x", "-1.0.0", "1.2.-3"] {
+            assert!(bad.parse::<Version>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn only_newer_versions_update() {
+        let slot = 1024;
+        assert_eq!(
+            manifest("1.3.0").is_update_for(&version("1.2.9"), slot),
+            Ok(true)
+        );
+        assert_eq!(
+            manifest("1.2.9").is_update_for(&version("1.2.9"), slot),
+            Ok(false)
+        );
+        assert_eq!(
+            manifest("1.2.8").is_update_for(&version("1.2.9"), slot),
+            Ok(false)
+        );
+        assert_eq!(
+            manifest("9.0.0").is_update_for(&version("1.0.0"), 8),
+            Err(OtaError::TooLarge { size: 27, max: 8 })
+        );
+    }
+
+    #[test]
+    fn verifies_image() {
+        let mut verifier = ImageVerifier::new(&manifest("1.0.0"));
+        for chunk in IMAGE.chunks(5) {
+            verifier.update(chunk).unwrap();
+        }
+        assert_eq!(verifier.finish(), Ok(()));
+
+        let mut verifier = ImageVerifier::new(&manifest("1.0.0"));
+        verifier.update(&IMAGE[..10]).unwrap();
+        assert_eq!(
+            verifier.finish(),
+            Err(OtaError::SizeMismatch {
+                expected: 27,
+                actual: 10
+            })
+        );
+
+        let mut verifier = ImageVerifier::new(&manifest("1.0.0"));
+        verifier.update(IMAGE).unwrap();
+        assert_eq!(
+            verifier.update(b"!"),
+            Err(OtaError::SizeMismatch {
+                expected: 27,
+                actual: 28
+            })
+        );
+
+        let mut verifier = ImageVerifier::new(&manifest("1.0.0"));
+        verifier.update(&IMAGE.to_ascii_uppercase()).unwrap();
+        assert_eq!(verifier.finish(), Err(OtaError::DigestMismatch));
+    }
+}
@@ -0,0 +1,70 @@
+//! HTTP server on an ephemeral local port that answers with canned responses,
+//! so the request logic can be tested over real sockets.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+pub struct TestServer {
+    pub port: u16,
+    requests: JoinHandle<Vec<String>>,
+}
+
+impl TestServer {
+    /// Serves one connection per entry of `responses`, in order, then stops.
+    pub fn start(responses: &[&str]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let responses: Vec<String> = responses.iter().map(|r| r.to_string()).collect();
+        let requests = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let request = read_request(&mut reader);
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+                    request
+                })
+                .collect()
+        });
+        TestServer { port, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{path}", self.port)
+    }
+
+    /// Requests received, head and body, once every response was sent.
+    pub fn requests(self) -> Vec<String> {
+        self.requests.join().unwrap()
+    }
+}
+
+/// Value of header `name` in a raw request.
+pub fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+fn read_request(reader: &mut impl BufRead) -> String {
+    let mut request = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+            request.push_str(&line);
+            break;
+        }
+        request.push_str(&line);
+    }
+    let len = header(&request, "content-length").map_or(0, |len| len.parse().unwrap());
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+    request.push_str(&String::from_utf8(body).unwrap());
+    request
+}
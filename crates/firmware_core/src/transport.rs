@@ -0,0 +1,198 @@
+use crate::cache::{FetchError, FetchOutcome, Validators, classify};
+use crate::http::{HttpError, Method, RequestSpec, Response, ServerConfig, Url, is_redirect};
+
+/// Something that can perform single HTTP requests: the device's network
+/// session, or a plain socket client on the host.
+pub trait ScheduleTransport {
+    type Error: From<HttpError> + From<FetchError>;
+
+    /// Performs exactly one request, redirects are returned as is.
+    fn send(&mut self, request: &RequestSpec, body: &[u8]) -> Result<Response, Self::Error>;
+
+    fn get(&mut self, url: &Url, headers: &[(String, String)]) -> Result<Response, Self::Error> {
+        self.send(&RequestSpec::new(Method::Get, url.clone(), headers), &[])
+    }
+
+    fn post(
+        &mut self,
+        url: &Url,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> Result<Response, Self::Error> {
+        self.send(&RequestSpec::new(Method::Post, url.clone(), headers), body)
+    }
+}
+
+/// Conditional GET of the schedule, following redirects as configured.
+pub fn fetch_schedule<T>(
+    transport: &mut T,
+    config: &ServerConfig,
+    validators: &Validators,
+) -> Result<FetchOutcome, T::Error>
+where
+    T: ScheduleTransport + ?Sized,
+{
+    let mut request = config.schedule_request()?;
+    validators.apply(&mut request);
+    loop {
+        let response = transport.send(&request, &[])?;
+        if !is_redirect(response.status) {
+            return Ok(classify(response)?);
+        }
+        request = config.follow_redirect(&request, response.status, response.header("location"))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum StubError {
+        Http(HttpError),
+        Fetch(FetchError),
+    }
+
+    impl From<HttpError> for StubError {
+        fn from(err: HttpError) -> Self {
+            StubError::Http(err)
+        }
+    }
+
+    impl From<FetchError> for StubError {
+        fn from(err: FetchError) -> Self {
+            StubError::Fetch(err)
+        }
+    }
+
+    /// Answers with canned responses and records what was sent.
+    #[derive(Default)]
+    struct Stub {
+        responses: VecDeque<Response>,
+        sent: Vec<(RequestSpec, Vec<u8>)>,
+    }
+
+    impl Stub {
+        fn new(
+            responses: impl IntoIterator<Item = (u16, &'static [(&'static str, &'static str)])>,
+        ) -> Self {
+            Stub {
+                responses: responses
+                    .into_iter()
+                    .map(|(status, headers)| Response {
+                        status,
+                        headers: headers
+                            .iter()
+                            .map(|&(name, value)| (name.to_string(), value.to_string()))
+                            .collect(),
+                        body: format!("body {status}").into_bytes(),
+                    })
+                    .collect(),
+                sent: Vec::new(),
+            }
+        }
+    }
+
+    impl ScheduleTransport for Stub {
+        type Error = StubError;
+
+        fn send(&mut self, request: &RequestSpec, body: &[u8]) -> Result<Response, StubError> {
+            self.sent.push((request.clone(), body.to_vec()));
+            Ok(self.responses.pop_front().expect("unexpected request"))
+        }
+    }
+
+    fn config() -> ServerConfig {
+        ServerConfig::new("https://example.com/schedule")
+            .unwrap()
+            .with_auth_token("secret")
+    }
+
+    #[test]
+    fn fetches_through_redirects() {
+        let mut stub = Stub::new([
+            (301, &[("Location", "/v2/schedule")][..]),
+            (200, &[("ETag", "\"v1\"")][..]),
+        ]);
+        let validators = Validators {
+            etag: Some("\"v0\"".to_string()),
+            last_modified: None,
+        };
+
+        let outcome = fetch_schedule(&mut stub, &config(), &validators).unwrap();
+        assert_eq!(
+            outcome,
+            FetchOutcome::Updated {
+                body: b"body 200".to_vec(),
+                validators: Validators {
+                    etag: Some("\"v1\"".to_string()),
+                    last_modified: None,
+                },
+            }
+        );
+
+        let urls: Vec<String> = stub.sent.iter().map(|(r, _)| r.url.to_string()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/schedule",
+                "https://example.com/v2/schedule"
+            ]
+        );
+        for (request, _) in &stub.sent {
+            assert_eq!(request.method, Method::Get);
+            assert_eq!(request.header("accept"), Some("application/json"));
+            assert_eq!(request.header("authorization"), Some("Bearer secret"));
+            assert_eq!(request.header("if-none-match"), Some("\"v0\""));
+        }
+    }
+
+    #[test]
+    fn not_modified_and_errors() {
+        let mut stub = Stub::new([(304, &[][..]), (502, &[][..])]);
+        let outcome = fetch_schedule(&mut stub, &config(), &Validators::default());
+        assert_eq!(outcome, Ok(FetchOutcome::NotModified));
+        let outcome = fetch_schedule(&mut stub, &config(), &Validators::default());
+        assert_eq!(outcome, Err(StubError::Fetch(FetchError::Status(502))));
+    }
+
+    #[test]
+    fn stops_redirect_loops() {
+        let redirect = (302, &[("Location", "/schedule")][..]);
+        let mut stub = Stub::new([redirect; 4]);
+        let outcome = fetch_schedule(&mut stub, &config(), &Validators::default());
+        assert_eq!(
+            outcome,
+            Err(StubError::Http(HttpError::TooManyRedirects(3)))
+        );
+        assert_eq!(stub.sent.len(), 4);
+    }
+
+    #[test]
+    fn refuses_plaintext_before_sending() {
+        let mut stub = Stub::default();
+        let config = ServerConfig::new("http://example.com/schedule").unwrap();
+        let outcome = fetch_schedule(&mut stub, &config, &Validators::default());
+        assert!(matches!(
+            outcome,
+            Err(StubError::Http(HttpError::InsecureScheme(_)))
+        ));
+        assert!(stub.sent.is_empty());
+    }
+
+    #[test]
+    fn post_sends_body() {
+        let mut stub = Stub::new([(204, &[][..])]);
+        let url = Url::parse("https://example.com/telemetry").unwrap();
+        let headers = [("Content-Type".to_string(), "application/json".to_string())];
+
+        let response = stub.post(&url, &headers, b"{}").unwrap();
+        assert_eq!(response.status, 204);
+        let (request, body) = &stub.sent[0];
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(body, b"{}");
+    }
+}
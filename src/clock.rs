@@ -0,0 +1,51 @@
+//! Wall clock for the now-line. The RTC keeps counting through deep sleep,
+//! SNTP sets it after power loss and corrects its drift on every wake.
+
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use esp_idf_svc::sntp::{EspSntp, SyncStatus};
+use log::info;
+
+/// 2024-01-01, anything earlier means the clock was never set.
+const MIN_VALID: Duration = Duration::from_secs(1_704_067_200);
+
+/// Sets the system clock over SNTP, waiting at most `timeout`. Needs the
+/// network to be up.
+pub fn sync(timeout: Duration) -> anyhow::Result<()> {
+    let sntp = EspSntp::new_default()?;
+    let started = Instant::now();
+    while sntp.get_sync_status() != SyncStatus::Completed {
+        if started.elapsed() > timeout {
+            return Err(anyhow::anyhow!("SNTP: no answer within {timeout:?}"));
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    info!("SNTP: clock set in {:?}", started.elapsed());
+    Ok(())
+}
+
+/// Applies a POSIX TZ string, e.g. `CET-1CEST,M3.5.0,M10.5.0/3`, to local
+/// times.
+pub fn set_timezone(tz: &str) {
+    std::env::set_var("TZ", tz);
+    // SAFETY: only reads TZ, which is set from the main task only.
+    unsafe { esp_idf_sys::tzset() };
+}
+
+/// Local time of day in hours (`13.5` is 13:30), `None` while the clock is
+/// not set.
+pub fn now_hours() -> Option<f32> {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    if since_epoch < MIN_VALID {
+        return None;
+    }
+
+    let secs = since_epoch.as_secs() as esp_idf_sys::time_t;
+    let mut local = esp_idf_sys::tm::default();
+    // SAFETY: both pointers are valid for the duration of the call.
+    if unsafe { esp_idf_sys::localtime_r(&secs, &mut local) }.is_null() {
+        return None;
+    }
+    Some(local.tm_hour as f32 + local.tm_min as f32 / 60.0)
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use embedded_graphics_components::screen::Node;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use firmware_core::cache::Validators;
+use firmware_core::http::{CaSource, ServerConfig};
+use firmware_core::wifi::{Network, StaticIp, WifiSettings};
+
+/// NVS namespace holding the per-device settings.
+pub const NVS_NAMESPACE: &str = "schedule";
+
+const KEY_URL: &str = "server_url";
+const KEY_TOKEN: &str = "auth_token";
+const KEY_CA_PEM: &str = "ca_pem";
+const KEY_TIMEOUT_MS: &str = "timeout_ms";
+const KEY_STATIC_IP: &str = "static_ip";
+const KEY_OTA_URL: &str = "ota_url";
+const KEY_OTA_CA_PEM: &str = "ota_ca_pem";
+const KEY_ETAG: &str = "etag";
+const KEY_LAST_MODIFIED: &str = "last_modified";
+const KEY_LAYOUT: &str = "layout";
+const KEY_ROOM_NAME: &str = "room_name";
+const KEY_SCREEN: &str = "screen";
+const KEY_LOGO: &str = "logo";
+const KEY_TIMEZONE: &str = "timezone";
+
+/// Short ID printed on the error screens to tell displays apart, from the
+/// last three bytes of the factory MAC.
+pub fn device_id() -> String {
+    let mut mac = [0u8; 6];
+    // SAFETY: writes exactly 6 bytes.
+    unsafe { esp_idf_sys::esp_efuse_mac_get_default(mac.as_mut_ptr()) };
+    format!("eink-{:02x}{:02x}{:02x}", mac[3], mac[4], mac[5])
+}
+
+pub fn open(nvs: EspDefaultNvsPartition) -> anyhow::Result<EspNvs<NvsDefault>> {
+    Ok(EspNvs::new(nvs, NVS_NAMESPACE, true)?)
+}
+
+pub fn get_string(nvs: &EspNvs<NvsDefault>, key: &str) -> anyhow::Result<Option<String>> {
+    let Some(len) = nvs.str_len(key)? else {
+        return Ok(None);
+    };
+    let mut buf = vec![0; len];
+    Ok(nvs.get_str(key, &mut buf)?.map(str::to_string))
+}
+
+pub fn get_blob(nvs: &EspNvs<NvsDefault>, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    let Some(len) = nvs.blob_len(key)? else {
+        return Ok(None);
+    };
+    let mut buf = vec![0; len];
+    Ok(nvs.get_blob(key, &mut buf)?.map(<[u8]>::to_vec))
+}
+
+/// Reads the schedule server settings, failing when no url is provisioned.
+pub fn load_server_config(nvs: &EspNvs<NvsDefault>) -> anyhow::Result<ServerConfig> {
+    let url = get_string(nvs, KEY_URL)?
+        .ok_or_else(|| anyhow::anyhow!("'{KEY_URL}' is not set in NVS"))?;
+
+    let mut config = ServerConfig::new(&url)?;
+    if let Some(token) = get_string(nvs, KEY_TOKEN)? {
+        config = config.with_auth_token(token);
+    }
+    if let Some(pem) = get_string(nvs, KEY_CA_PEM)? {
+        config = config.with_ca(CaSource::Pinned(pem));
+    }
+    if let Some(timeout_ms) = nvs.get_u32(KEY_TIMEOUT_MS)? {
+        config = config.with_timeout(Duration::from_millis(timeout_ms.into()));
+    }
+
+    Ok(config)
+}
+
+/// Update manifest server, OTA is disabled when `ota_url` is unset. It is
+/// trusted through its own `ota_ca_pem`, or the certificate bundle, as
+/// updates are often hosted apart from the schedule.
+pub fn load_ota_config(nvs: &EspNvs<NvsDefault>) -> anyhow::Result<Option<ServerConfig>> {
+    let Some(url) = get_string(nvs, KEY_OTA_URL)? else {
+        return Ok(None);
+    };
+
+    let mut config = ServerConfig::new(&url)?;
+    if let Some(pem) = get_string(nvs, KEY_OTA_CA_PEM)? {
+        config = config.with_ca(CaSource::Pinned(pem));
+    }
+    if let Some(timeout_ms) = nvs.get_u32(KEY_TIMEOUT_MS)? {
+        config = config.with_timeout(Duration::from_millis(timeout_ms.into()));
+    }
+
+    Ok(Some(config))
+}
+
+/// POSIX TZ string the now-line's local time is in, UTC when unset.
+pub fn load_timezone(nvs: &EspNvs<NvsDefault>) -> anyhow::Result<String> {
+    Ok(get_string(nvs, KEY_TIMEZONE)?.unwrap_or_else(|| "UTC0".to_string()))
+}
+
+/// What the panel shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    /// Multi-day hour grid, the default.
+    Grid,
+    /// Door sign for one room.
+    Room { name: String },
+    /// Chronological list, for many short meetings.
+    Agenda,
+    /// Composed from the JSON description in `screen`, with the BMP image
+    /// in `logo` for its logo.
+    Custom { screen: Node, logo: Option<Vec<u8>> },
+}
+
+/// `layout` is `grid`, `agenda`, `room`, titled with `room_name`, or
+/// `custom`, described by `screen`.
+pub fn load_layout(nvs: &EspNvs<NvsDefault>) -> anyhow::Result<Layout> {
+    match get_string(nvs, KEY_LAYOUT)?.as_deref() {
+        None | Some("grid") => Ok(Layout::Grid),
+        Some("agenda") => Ok(Layout::Agenda),
+        Some("room") => Ok(Layout::Room {
+            name: get_string(nvs, KEY_ROOM_NAME)?.unwrap_or_default(),
+        }),
+        Some("custom") => {
+            let json = get_string(nvs, KEY_SCREEN)?
+                .ok_or_else(|| anyhow::anyhow!("'{KEY_SCREEN}' is not set in NVS"))?;
+            let node = serde_json::from_str(&json)
+                .map_err(|err| anyhow::anyhow!("Invalid '{KEY_SCREEN}' in NVS: {err}"))?;
+            Ok(Layout::Custom {
+                screen: node,
+                logo: get_blob(nvs, KEY_LOGO)?,
+            })
+        }
+        Some(other) => Err(anyhow::anyhow!("Unknown '{KEY_LAYOUT}' '{other}' in NVS")),
+    }
+}
+
+/// Fallback networks are stored as `wifi_ssid0`/`wifi_pass0` .. `wifi_ssid3`,
+/// at least one is required.
+const MAX_NETWORKS: usize = 4;
+
+pub fn load_wifi_settings(nvs: &EspNvs<NvsDefault>) -> anyhow::Result<WifiSettings> {
+    let mut networks = Vec::new();
+    for i in 0..MAX_NETWORKS {
+        if let Some(ssid) = get_string(nvs, &format!("wifi_ssid{i}"))? {
+            let password = get_string(nvs, &format!("wifi_pass{i}"))?.unwrap_or_default();
+            networks.push(Network { ssid, password });
+        }
+    }
+    if networks.is_empty() {
+        return Err(anyhow::anyhow!("'wifi_ssid0' is not set in NVS"));
+    }
+
+    let static_ip = get_string(nvs, KEY_STATIC_IP)?
+        .map(|s| StaticIp::parse(&s))
+        .transpose()?;
+
+    Ok(WifiSettings {
+        networks,
+        static_ip,
+        ..Default::default()
+    })
+}
+
+pub fn load_validators(nvs: &EspNvs<NvsDefault>) -> anyhow::Result<Validators> {
+    Ok(Validators {
+        etag: get_string(nvs, KEY_ETAG)?,
+        last_modified: get_string(nvs, KEY_LAST_MODIFIED)?,
+    })
+}
+
+/// Remembers the validators of the schedule that is now on the panel.
+pub fn store_validators(
+    nvs: &mut EspNvs<NvsDefault>,
+    validators: &Validators,
+) -> anyhow::Result<()> {
+    for (key, value) in [
+        (KEY_ETAG, &validators.etag),
+        (KEY_LAST_MODIFIED, &validators.last_modified),
+    ] {
+        match value {
+            Some(value) => nvs.set_str(key, value)?,
+            None => {
+                nvs.remove(key)?;
+            }
+        }
+    }
+    Ok(())
+}
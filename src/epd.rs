@@ -1,157 +1,384 @@
-use embedded_graphics::image::Image;
-use embedded_graphics::mono_font::ascii::FONT_10X20;
-use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
-use embedded_graphics::pixelcolor::Rgb565;
-use embedded_graphics::prelude::Point;
+use std::ptr::{addr_of, addr_of_mut};
+use std::time::{Duration, Instant};
+
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10, FONT_8X13};
+use embedded_graphics::mono_font::{MonoFont, MonoTextStyle};
 use embedded_graphics::prelude::*;
-use embedded_graphics::primitives::Line;
-use embedded_graphics::text::{LineHeight, Text};
-use embedded_graphics::{
-    prelude::*,
-    primitives::{PrimitiveStyleBuilder, Rectangle},
-};
-use embedded_text::{alignment::HorizontalAlignment, style::TextBoxStyleBuilder, TextBox};
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics_components::dirty::Refresh;
+use embedded_graphics_components::image_box::ImageBox;
+use embedded_graphics_components::unified_color::UnifiedColor;
+use embedded_text::alignment::HorizontalAlignment;
+use embedded_text::style::{HeightMode, TextBoxStyleBuilder};
+use embedded_text::TextBox;
 use epd_waveshare::color::TriColor;
-#[cfg(feature = "wokwi")]
-use epd_waveshare::epd2in9_v2::{Display2in9 as Display, Epd2in9 as Epd};
-#[cfg(not(feature = "wokwi"))]
-use epd_waveshare::epd7in5b_v3::{Display7in5 as Display, Epd7in5 as Epd};
-use epd_waveshare::prelude::WaveshareDisplay;
-use esp_backtrace as _;
-use esp_idf_hal::delay::Delay;
-use esp_idf_hal::gpio;
-use esp_idf_hal::gpio::AnyOutputPin;
-use esp_idf_hal::gpio::PinDriver;
-use esp_idf_hal::prelude::*;
+use epd_waveshare::prelude::DisplayRotation;
+use esp_idf_hal::delay::FreeRtos;
+use esp_idf_hal::gpio::{self, PinDriver};
 use esp_idf_hal::spi;
-use esp_idf_hal::spi::SPI3;
-use log::info;
-use tinybmp::Bmp;
+use esp_idf_hal::units::Hertz;
+use log::{info, warn};
 
-use crate::epd_pins::EpdHardwarePins;
-// use crate::unified_color::UnifiedColor;
+use crate::epd_pins::{self, EpdHardwarePins};
+use crate::panel::{self, Canvas, Out, Panel};
 
-pub fn epd_start_render_text(
-    EpdHardwarePins {
-        spi,
-        sclk,
-        mosi,
-        cs,
-        busy_in,
-        rst,
-        dc,
-        pwr,
-    }: EpdHardwarePins,
-    text: &str,
-    point: Point,
-) -> anyhow::Result<()> {
-    let mut pwr = PinDriver::output(pwr)?;
-    pwr.set_high()?;
-
-    let mut spidd = spi::SpiDeviceDriver::new_single(
-        spi,
-        sclk,
-        mosi,
-        Option::<gpio::AnyIOPin>::None,
-        Some(cs),
-        &spi::config::DriverConfig::new(),
-        &spi::config::Config::new().baudrate(115200.Hz()),
-    )?;
-
-    let mut delay = Delay::new(100);
-
-    let mut epd = Epd::new(
-        &mut spidd,
-        PinDriver::input(busy_in)?,
-        PinDriver::output(dc)?,
-        PinDriver::output(rst)?,
-        &mut delay,
-        None,
-    )?;
-
-    epd.wake_up(&mut spidd, &mut delay)?;
-
-    epd.clear_frame(&mut spidd, &mut delay)?;
-
-    let display = Box::new(Display::default());
-
-    epd.update_and_display_frame(&mut spidd, display.buffer(), &mut delay)
-        .expect("Failed to update and display EPD frame");
-
-    info!("Frame updated and displayed");
-
-    delay.delay_ms(1000);
-    epd.sleep(&mut spidd, &mut delay)
-        .expect("Failed to put EPD to sleep");
-    Ok(())
-}
-
-pub fn epd_start_render_bmp(
-    EpdHardwarePins {
-        spi,
-        sclk,
-        mosi,
-        cs,
-        busy_in,
-        rst,
-        dc,
-        pwr,
-    }: EpdHardwarePins,
-) -> anyhow::Result<()> {
-    let mut pwr = PinDriver::output(pwr)?;
-    pwr.set_high()?;
+/// Fast enough that sending a 7.5" tri-colour frame takes well under a
+/// second, slow enough for the ribbon cable.
+pub const DEFAULT_SPI_CLOCK: Hertz = Hertz(4_000_000);
+
+/// How long the controller may stay busy after power-up.
+const POWER_UP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What is on the panel, kept across deep sleep (not power loss) so the next
+/// wake can refresh only what changed.
+#[link_section = ".rtc.data"]
+static mut RTC_REFRESH: RtcRefresh = RtcRefresh::EMPTY;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct RtcRefresh {
+    magic: u32,
+    partials_since_full: u32,
+    nowline_time: f32,
+}
+
+impl RtcRefresh {
+    const MAGIC: u32 = 0x5246_5348;
+    const EMPTY: Self = RtcRefresh {
+        magic: 0,
+        partials_since_full: 0,
+        nowline_time: 0.0,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefreshState {
+    pub partials_since_full: u32,
+    /// `None` when the frame was drawn without a clock.
+    pub nowline_time: Option<f32>,
+}
+
+/// State of the last frame shown, `None` after power loss.
+pub fn load_refresh_state() -> Option<RefreshState> {
+    // SAFETY: RTC_REFRESH is only accessed from the main task.
+    let rtc = unsafe { addr_of!(RTC_REFRESH).read() };
+    (rtc.magic == RtcRefresh::MAGIC).then_some(RefreshState {
+        partials_since_full: rtc.partials_since_full,
+        nowline_time: Some(rtc.nowline_time).filter(|time| !time.is_nan()),
+    })
+}
+
+pub fn store_refresh_state(state: RefreshState) {
+    let rtc = RtcRefresh {
+        magic: RtcRefresh::MAGIC,
+        partials_since_full: state.partials_since_full,
+        nowline_time: state.nowline_time.unwrap_or(f32::NAN),
+    };
+    // SAFETY: RTC_REFRESH is only accessed from the main task.
+    unsafe { addr_of_mut!(RTC_REFRESH).write(rtc) };
+}
+
+/// Forgets the last frame, e.g. after something else was shown.
+pub fn clear_refresh_state() {
+    // SAFETY: RTC_REFRESH is only accessed from the main task.
+    unsafe { addr_of_mut!(RTC_REFRESH).write(RtcRefresh::EMPTY) };
+}
+
+/// Panel supply, switched off again when dropped.
+struct PowerSwitch(Option<Out>);
+
+impl Drop for PowerSwitch {
+    fn drop(&mut self) {
+        if let Some(pwr) = &mut self.0 {
+            let _ = pwr.set_low();
+        }
+    }
+}
+
+/// Waits for the controller to report idle on BUSY, read with the board's
+/// polarity. Only warns on timeout, the driver waits again before each
+/// command.
+fn wait_until_idle(busy: &panel::Busy, active_low: bool, timeout: Duration) {
+    let started = Instant::now();
+    while busy.is_low() == active_low {
+        if started.elapsed() > timeout {
+            warn!("EPD: still busy {timeout:?} after power-up");
+            return;
+        }
+        FreeRtos::delay_ms(10);
+    }
+}
+
+/// A powered and initialised panel. Dropping it puts the controller to
+/// sleep and switches the supply off, whether rendering succeeded or not.
+pub struct EpdSession {
+    panel: Box<dyn Panel>,
+    asleep: bool,
+    // Dropped after `panel`, so the controller is asleep before power goes.
+    _power: PowerSwitch,
+}
+
+impl EpdSession {
+    pub fn open(
+        EpdHardwarePins {
+            panel: kind,
+            spi,
+            sclk,
+            mosi,
+            cs,
+            busy_in,
+            busy_active_low,
+            rst,
+            dc,
+            pwr,
+        }: EpdHardwarePins,
+        rotation: DisplayRotation,
+        spi_clock: Hertz,
+    ) -> anyhow::Result<Self> {
+        let power = PowerSwitch(epd_pins::power_on(pwr)?);
+
+        let spidd = spi::SpiDeviceDriver::new_single(
+            spi,
+            sclk,
+            mosi,
+            Option::<gpio::AnyIOPin>::None,
+            Some(cs),
+            &spi::config::DriverConfig::new(),
+            &spi::config::Config::new().baudrate(spi_clock),
+        )?;
 
-    let mut spidd = spi::SpiDeviceDriver::new_single(
-        spi,
-        sclk,
-        mosi,
-        Option::<gpio::AnyIOPin>::None,
-        Some(cs),
-        &spi::config::DriverConfig::new(),
-        &spi::config::Config::new().baudrate(115200.Hz()),
-    )?;
+        let busy = PinDriver::input(busy_in)?;
+        wait_until_idle(&busy, busy_active_low, POWER_UP_TIMEOUT);
 
-    let mut delay = Delay::new(100);
+        let mut panel = panel::open(
+            kind,
+            spidd,
+            busy,
+            PinDriver::output(dc)?,
+            PinDriver::output(rst)?,
+            rotation,
+        )?;
+        panel.init()?;
 
-    let mut epd = Epd::new(
-        &mut spidd,
-        PinDriver::input(busy_in)?,
-        PinDriver::output(dc)?,
-        PinDriver::output(rst)?,
-        &mut delay,
-        None,
-    )?;
+        Ok(EpdSession {
+            panel,
+            asleep: false,
+            _power: power,
+        })
+    }
 
-    epd.wake_up(&mut spidd, &mut delay)?;
+    pub fn size(&self) -> Size {
+        self.panel.size()
+    }
 
-    epd.clear_frame(&mut spidd, &mut delay)?;
+    pub fn panel(&mut self) -> &mut dyn Panel {
+        &mut *self.panel
+    }
 
-    let mut display = Box::new(Display::default());
+    /// Draws a frame and shows it with a full refresh.
+    pub fn render(
+        &mut self,
+        scene: impl FnMut(&mut Canvas<'_>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.render_with(scene, &Refresh::Full)
+    }
 
-    // display
-    //     .clear(UnifiedColor::White.into())
-    //     .expect("Failed to clear display buffer");
+    /// Draws the whole frame and refreshes the panel as `refresh` asks, all
+    /// partial areas at once.
+    pub fn render_with(
+        &mut self,
+        mut scene: impl FnMut(&mut Canvas<'_>) -> anyhow::Result<()>,
+        refresh: &Refresh,
+    ) -> anyhow::Result<()> {
+        if *refresh == Refresh::None {
+            return Ok(());
+        }
 
-    // let bmp: Bmp<Rgb565> = Bmp::from_slice(include_bytes!("./assets/rust-pride.bmp")).unwrap();
+        self.panel.draw(&mut scene)?;
+        match refresh {
+            Refresh::Partial(areas) => self.panel.update_partial(areas)?,
+            _ => self.panel.update_full()?,
+        }
+        info!("Frame updated and displayed ({refresh:?})");
+        Ok(())
+    }
 
-    // display.draw_iter(bmp.pixels().map(|pixel| {
-    //     let point = pixel.0;
-    //     let color = pixel.1;
+    /// Puts the panel to sleep and powers it off, reporting what dropping
+    /// the session would swallow.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.asleep = true;
+        self.panel.sleep()
+    }
+}
+
+impl Drop for EpdSession {
+    fn drop(&mut self) {
+        if !self.asleep {
+            if let Err(err) = self.panel.sleep() {
+                warn!("EPD: failed to put the panel to sleep: {err:#}");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl FontSize {
+    fn font(self) -> &'static MonoFont<'static> {
+        match self {
+            FontSize::Small => &FONT_6X10,
+            FontSize::Medium => &FONT_8X13,
+            FontSize::Large => &FONT_10X20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessagePosition {
+    /// Text box starts here and extends to the right and bottom edges.
+    At(Point),
+    /// Text box is centred on the screen.
+    Centered,
+}
+
+/// Multi-line text shown on its own screen: errors, provisioning hints and
+/// the like. Lines are wrapped to the screen width.
+#[derive(Debug, Clone, Copy)]
+pub struct Message<'a> {
+    pub text: &'a str,
+    pub position: MessagePosition,
+    pub alignment: HorizontalAlignment,
+    pub font: FontSize,
+    /// White text on a chromatic (black on B/W panels) band.
+    pub highlight: bool,
+}
+
+impl<'a> Message<'a> {
+    const MARGIN: u32 = 8;
+    const HIGHLIGHT_PADDING: i32 = 4;
+
+    pub fn new(text: &'a str) -> Self {
+        Message {
+            text,
+            position: MessagePosition::Centered,
+            alignment: HorizontalAlignment::Center,
+            font: FontSize::Large,
+            highlight: false,
+        }
+    }
+
+    pub fn at(mut self, point: Point) -> Self {
+        self.position = MessagePosition::At(point);
+        self
+    }
+
+    pub fn align(mut self, alignment: HorizontalAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
 
-    //     let new_color = UnifiedColor::from_rgb565(color).into();
+    pub fn font(mut self, font: FontSize) -> Self {
+        self.font = font;
+        self
+    }
 
-    //     Pixel(point, new_color)
-    // }))?;
+    pub fn highlight(mut self, highlight: bool) -> Self {
+        self.highlight = highlight;
+        self
+    }
 
-    // // image.draw(display.as_mut()).expect("Failed to draw image");
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = TriColor>,
+    {
+        let screen = display.bounding_box();
+        let (origin, width) = match self.position {
+            MessagePosition::At(point) => {
+                (point, (screen.size.width as i32 - point.x).max(0) as u32)
+            }
+            MessagePosition::Centered => (
+                Point::new(Self::MARGIN as i32, 0),
+                screen.size.width.saturating_sub(Self::MARGIN * 2),
+            ),
+        };
+
+        let (text_color, band_color) = if self.highlight {
+            (TriColor::White, Some(TriColor::Chromatic))
+        } else {
+            (TriColor::Black, None)
+        };
+        let character_style = MonoTextStyle::new(self.font.font(), text_color);
+        let textbox_style = TextBoxStyleBuilder::new()
+            .height_mode(HeightMode::FitToText)
+            .alignment(self.alignment)
+            .build();
+
+        let mut text_box = TextBox::with_textbox_style(
+            self.text,
+            Rectangle::new(origin, Size::new(width, 0)),
+            character_style,
+            textbox_style,
+        );
+        if self.position == MessagePosition::Centered {
+            let height = text_box.bounding_box().size.height as i32;
+            text_box.translate_mut(Point::new(0, (screen.size.height as i32 - height) / 2));
+        }
+
+        if let Some(band_color) = band_color {
+            text_box
+                .bounding_box()
+                .offset(Self::HIGHLIGHT_PADDING)
+                .into_styled(PrimitiveStyle::with_fill(band_color))
+                .draw(display)?;
+        }
+        text_box.draw(display)?;
+        Ok(())
+    }
+}
+
+/// Shows `message` alone on a white screen.
+pub fn epd_render_message(pins: EpdHardwarePins, message: &Message<'_>) -> anyhow::Result<()> {
+    let mut epd = EpdSession::open(pins, DisplayRotation::Rotate0, DEFAULT_SPI_CLOCK)?;
+
+    epd.render(|canvas| {
+        canvas.clear(TriColor::White)?;
+        message.draw(canvas)?;
+        Ok(())
+    })?;
+
+    epd.finish()
+}
+
+/// Shows `text` left aligned at `point`.
+pub fn epd_start_render_text(
+    pins: EpdHardwarePins,
+    text: &str,
+    point: Point,
+) -> anyhow::Result<()> {
+    epd_render_message(
+        pins,
+        &Message::new(text)
+            .at(point)
+            .align(HorizontalAlignment::Left)
+            .font(FontSize::Medium),
+    )
+}
 
-    // epd.update_and_display_frame(&mut spidd, display.buffer(), &mut delay)
-    //     .expect("Failed to update and display EPD frame");
+/// Shows the BMP image `bmp` fitted to the whole panel.
+pub fn epd_start_render_bmp(pins: EpdHardwarePins, bmp: &[u8]) -> anyhow::Result<()> {
+    let mut epd = EpdSession::open(pins, DisplayRotation::Rotate0, DEFAULT_SPI_CLOCK)?;
+    let image = ImageBox::new(Point::zero(), epd.size(), bmp, |color| match color {
+        UnifiedColor::Black => TriColor::Black,
+        UnifiedColor::White => TriColor::White,
+        UnifiedColor::Chromatic => TriColor::Chromatic,
+    })?;
 
-    // info!("Frame updated and displayed");
+    epd.render(|canvas| Ok(image.draw(canvas)?))?;
 
-    // delay.delay_ms(1000);
-    // epd.sleep(&mut spidd, &mut delay)
-    //     .expect("Failed to put EPD to sleep");
-    Ok(())
+    epd.finish()
 }
@@ -1,67 +1,85 @@
 use esp_idf_hal::{
-    gpio::{AnyInputPin, AnyOutputPin}, modem::Modem, peripherals::Peripherals, spi::SPI3
+    gpio::{AnyInputPin, AnyOutputPin, PinDriver},
+    modem::Modem,
+    peripherals::Peripherals,
+    spi::SPI3,
 };
+use esp_idf_svc::eventloop::EspSystemEventLoop;
+use firmware_core::board::{self, BoardProfile};
 use log::info;
-use esp_idf_svc::{eventloop::EspSystemEventLoop};
 
-use crate::epd::epd_start_render_text;
+use crate::panel::{Out, PanelKind};
+
+/// Board this firmware is built for, selected by cargo feature.
+pub const BOARD: &BoardProfile = if cfg!(feature = "wokwi") {
+    &board::WOKWI
+} else if cfg!(feature = "board-waveshare") {
+    &board::WAVESHARE_DRIVER_BOARD
+} else if cfg!(feature = "board-lilygo-t5") {
+    &board::LILYGO_T5
+} else {
+    &board::CUSTOM_PCB
+};
 
 pub struct EpdHardwarePins {
+    pub panel: PanelKind,
     pub spi: SPI3,
     pub sclk: AnyOutputPin,
     pub mosi: AnyOutputPin,
     pub cs: AnyOutputPin,
     pub busy_in: AnyInputPin,
+    /// BUSY reads low while the controller is busy.
+    pub busy_active_low: bool,
     pub rst: AnyOutputPin,
     pub dc: AnyOutputPin,
-    pub pwr: AnyOutputPin,
+    pub pwr: Option<AnyOutputPin>,
 }
 
 pub struct NetParts {
     pub modem: Modem,
     pub sysloop: EspSystemEventLoop,
 }
-/// Retrieves the hardware pins for the EPD display.
+
+/// Retrieves the hardware pins for the EPD display, as described by [`BOARD`].
 pub fn get_pins() -> anyhow::Result<(EpdHardwarePins, NetParts)> {
-    let peripherals = Peripherals::take()?;
+    BOARD.validate()?;
+    info!("EPD_CONFIG: Using board profile '{}'.", BOARD.name);
 
-    let modem = peripherals.modem;
+    let peripherals = Peripherals::take()?;
     let sysloop = EspSystemEventLoop::take()?;
-    let sclk: AnyOutputPin = peripherals.pins.gpio18.into();
-    let cs: AnyOutputPin = peripherals.pins.gpio5.into();
-    let busy_in: AnyInputPin = peripherals.pins.gpio4.into();
-    let pwr: AnyOutputPin = peripherals.pins.gpio2.into();
 
-    let (mosi, rst, dc) = if cfg!(feature = "wokwi") {
-        info!("EPD_CONFIG: Using Wokwi pinout for EPD.");
-        (
-            peripherals.pins.gpio19.into(), // Wokwi MOSI
-            peripherals.pins.gpio21.into(), // Wokwi RST
-            peripherals.pins.gpio23.into(), // Wokwi DC
-        )
-    } else {
-        info!("EPD_CONFIG: Using Physical hardware pinout for EPD.");
-        (
-            peripherals.pins.gpio23.into(), // Physical MOSI
-            peripherals.pins.gpio16.into(), // Physical RST
-            peripherals.pins.gpio17.into(), // Physical DC
-        )
-    };
+    // SAFETY: `peripherals.pins` is never used, and the profile was validated
+    // above, so each GPIO below is handed out exactly once.
+    let output = |gpio: u8| unsafe { AnyOutputPin::new(gpio as i32) };
+    let input = |gpio: u8| unsafe { AnyInputPin::new(gpio as i32) };
 
     let epd = EpdHardwarePins {
+        panel: BOARD.panel,
         spi: peripherals.spi3,
-        sclk,
-        mosi,
-        cs,
-        busy_in,
-        rst,
-        dc,
-        pwr,
+        sclk: output(BOARD.spi.sclk),
+        mosi: output(BOARD.spi.mosi),
+        cs: output(BOARD.spi.cs),
+        busy_in: input(BOARD.busy),
+        busy_active_low: BOARD.busy_active_low,
+        rst: output(BOARD.rst),
+        dc: output(BOARD.dc),
+        pwr: BOARD.power_enable.map(output),
     };
     let net_parts = NetParts {
-        modem,
+        modem: peripherals.modem,
         sysloop,
     };
 
     Ok((epd, net_parts))
 }
+
+/// Switches the panel supply on, if the board has a power-enable pin. Keep
+/// the returned driver alive for as long as the panel is in use.
+pub fn power_on(pwr: Option<AnyOutputPin>) -> anyhow::Result<Option<Out>> {
+    pwr.map(|pin| {
+        let mut pwr = PinDriver::output(pin)?;
+        pwr.set_high()?;
+        Ok(pwr)
+    })
+    .transpose()
+}
@@ -1,4 +1,8 @@
+pub mod clock;
+pub mod config;
 pub mod epd;
 pub mod epd_pins;
+pub mod ota;
+pub mod panel;
 // pub mod unified_color;
 pub mod wifilib;
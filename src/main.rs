@@ -1,23 +1,64 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use anyhow::Context;
 use embedded_graphics::prelude::*;
 
 use embedded_graphics_components::unified_color::UnifiedColor;
 use epd_waveshare::color::TriColor;
-#[cfg(feature = "wokwi")]
-use epd_waveshare::epd2in9_v2::{Display2in9 as Display, Epd2in9 as Epd};
-#[cfg(not(feature = "wokwi"))]
-use epd_waveshare::epd7in5b_v3::{Display7in5 as Display, Epd7in5 as Epd};
-use epd_waveshare::prelude::WaveshareDisplay;
+use epd_waveshare::prelude::DisplayRotation;
 
-use embedded_graphics_components::battery_indicator::BatteryIndicator;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics_components::agenda_list::AgendaList;
+use embedded_graphics_components::component::Component;
+use embedded_graphics_components::dirty::{DirtyRegions, Refresh, RefreshPolicy};
+use embedded_graphics_components::error_screen::{ErrorKind, ErrorScreen};
+use embedded_graphics_components::event::Event;
+use embedded_graphics_components::room_status::RoomStatus;
 use embedded_graphics_components::schedule_table::ScheduleTable;
+use embedded_graphics_components::screen::{Node, ScreenData};
+use embedded_graphics_components::theme::Theme;
+use embedded_graphics_components::time_grid::{SlotLength, TimeGrid};
 use esp_backtrace as _;
-use esp_eink_schedule::epd_pins::{self, EpdHardwarePins};
-use esp_idf_hal::delay::Delay;
-use esp_idf_hal::gpio::{self, PinDriver};
-use esp_idf_hal::prelude::*;
-use esp_idf_hal::spi;
+use esp_eink_schedule::clock;
+use esp_eink_schedule::config::{self, Layout};
+use esp_eink_schedule::epd::{self, EpdSession, RefreshState, DEFAULT_SPI_CLOCK};
+use esp_eink_schedule::epd_pins::{self, EpdHardwarePins, NetParts};
+use esp_eink_schedule::ota;
+use esp_eink_schedule::panel::{self, Canvas};
+use esp_eink_schedule::wifilib::NetworkSession;
+use esp_idf_hal::reset::restart;
 use esp_idf_svc::nvs::EspDefaultNvsPartition;
-use log::info;
+use firmware_core::cache::{FetchError, FetchOutcome, Validators};
+use firmware_core::http::HttpError;
+use firmware_core::transport::fetch_schedule;
+use log::{error, info, warn};
+
+/// Deep sleep between refreshes.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Deep sleep after a failure, before trying again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Longest to wait for SNTP, the RTC's time is used without it.
+const SNTP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Every screen is drawn in portrait.
+const ROTATION: DisplayRotation = DisplayRotation::Rotate90;
+const TODAY: &str = "02.01.2025";
+
+const EVENTS: [Event<'static>; 12] = [
+    Event::new("01.01.2025", 6.0, 12.25, "xsichkaruk"),
+    Event::new("01.01.2025", 12.5, 14.0, "xchaban"),
+    Event::new("01.01.2025", 14.5, 17.0, "xchaban"),
+    Event::new("02.01.2025", 10.25, 10.75, "xchaban"),
+    Event::new("02.01.2025", 11.5, 13.25, "xtodorov"),
+    Event::new("02.01.2025", 13.5, 15.0, "xchaban"),
+    Event::new("03.01.2025", 10.0, 12.0, "xchaban"),
+    Event::new("03.01.2025", 12.25, 14.5, "xchaban"),
+    Event::new("03.01.2025", 15.0, 16.0, "xchaban"),
+    Event::new("01.01.2025", 17.0, 17.25, "xchaban"),
+    Event::new("02.01.2025", 17.0, 17.5, "xchaban"),
+    Event::new("03.01.2025", 17.0, 18.00, "xchaban"),
+];
 
 fn unif_color_converter(color: UnifiedColor) -> TriColor {
     match color {
@@ -30,128 +71,310 @@ fn unif_color_converter(color: UnifiedColor) -> TriColor {
 fn main() -> anyhow::Result<()> {
     esp_idf_sys::link_patches();
     esp_idf_svc::log::EspLogger::initialize_default();
-    let _nvs = EspDefaultNvsPartition::take()?;
+    let nvs = EspDefaultNvsPartition::take()?;
 
     info!("Starting EPD example");
 
-    let (epd_pins, _net) = epd_pins::get_pins()?;
-
-    let EpdHardwarePins {
-        spi,
-        sclk,
-        mosi,
-        cs,
-        busy_in,
-        rst,
-        dc,
-        pwr,
-    }: EpdHardwarePins = epd_pins;
-
-    let mut pwr = PinDriver::output(pwr)?;
-    pwr.set_high()?;
-
-    let mut spidd = spi::SpiDeviceDriver::new_single(
-        spi,
-        sclk,
-        mosi,
-        Option::<gpio::AnyIOPin>::None,
-        Some(cs),
-        &spi::config::DriverConfig::new(),
-        &spi::config::Config::new().baudrate(115200.Hz()),
-    )?;
-
-    let mut delay = Delay::new(100);
-
-    let mut epd = Epd::new(
-        &mut spidd,
-        PinDriver::input(busy_in)?,
-        PinDriver::output(dc)?,
-        PinDriver::output(rst)?,
-        &mut delay,
-        None,
-    )?;
-
-    epd.wake_up(&mut spidd, &mut delay)?;
-
-    let mut display = Box::new(Display::default());
-    display.set_rotation(epd_waveshare::prelude::DisplayRotation::Rotate90);
-
-    // Get display dimensions for calculations
-    let display_width = display.bounding_box().size.width;
-    let display_height = display.bounding_box().size.height;
-
-    // --- ScheduleTable parameters ---
-    let header_height = 40;
-    let time_col_width = 80;
-    let num_date_cols = 3;
-    // Number of data rows: this now controls how many rows are allocated visually.
-    // Ensure this value is chosen such that `(display_height - header_height)` is divisible by `num_data_rows`
-    // to avoid rounding issues if you want perfect pixel alignment.
-    // For a 128px height display with 40px header, you have 88px left.
-    // 88 / 12 = 7.33, so 12 is problematic.
-    // Let's re-evaluate for clean division: if display_height is 128 and header_height is 40,
-    // we have 88px for rows.
-    // If you want to show, say, 11 hours (6 to 17), that's 11 rows. 88 / 11 = 8px per hour.
-    // If you want to show 12 hours (6 to 18), 88 / 12 = 7.33. Let's stick with 12 if you desire that range,
-    // and accept potential rounding that `embedded-graphics` handles.
-    // Or adjust range, or adjust header_height/total_height to make it divisible.
-    // For simplicity with given values, we'll keep num_data_rows = 12 as per original table height scaling.
-    let num_data_rows = 12;
-
-    let battery_bar_height: u32 = 5; // Высота полосы батареи внизу
-
-    let y_pos_offset = 10;
-    let nowline_time = 13.5;
-
-    let header_texts = ["Time", "01.01.2025", "02.01.2025", "03.01.2025"];
-    let time_range = 6..=18; // From 6:00 to 18:00, which is 13 hours/rows effectively
-
-    let time_intervals = [
-        ("01.01.2025", 6.0, 12.25, "xsichkaruk"),
-        ("01.01.2025", 12.5, 14.0, "xchaban"),
-        ("01.01.2025", 14.5, 17.0, "xchaban"),
-        ("02.01.2025", 10.25, 10.75, "xchaban"),
-        ("02.01.2025", 11.5, 13.25, "xtodorov"),
-        ("02.01.2025", 13.5, 15.0, "xchaban"),
-        ("03.01.2025", 10.0, 12.0, "xchaban"),
-        ("03.01.2025", 12.25, 14.5, "xchaban"),
-        ("03.01.2025", 15.0, 16.0, "xchaban"),
-        ("01.01.2025", 17.0, 17.25, "xchaban"),
-        ("02.01.2025", 17.0, 17.5, "xchaban"),
-        ("03.01.2025", 17.0, 18.00, "xchaban"),
-    ];
-
-    ScheduleTable::new(
-        Point::new(0, battery_bar_height as i32 + 20), // Table starts at top-left of the display
-        Size::new(display_width, display_height - battery_bar_height), // Table occupies full display
-        header_height,
-        time_col_width,
-        num_date_cols,
-        num_data_rows, // Use dynamic row count
-        y_pos_offset,
-        nowline_time,
-        header_texts,
-        time_range,
-        time_intervals,
-        unif_color_converter,
-    )
-    .draw(display.as_mut())?;
+    let (epd_pins, net) = epd_pins::get_pins()?;
+    let mut epd_pins = Some(epd_pins);
+
+    let interval = match run(nvs, net, &mut epd_pins) {
+        Ok(()) => REFRESH_INTERVAL,
+        Err(err) => {
+            error!("{err:#}");
+            // Without pins left the panel itself failed, nothing to show on.
+            if let Some(pins) = epd_pins.take() {
+                if let Err(err) = show_error(pins, &err) {
+                    error!("Showing the error screen failed: {err:#}");
+                }
+            }
+            RETRY_INTERVAL
+        }
+    };
+
+    info!("Sleeping for {}s", interval.as_secs());
+    // SAFETY: plain FFI call, does not return.
+    unsafe { esp_idf_sys::esp_deep_sleep(interval.as_micros() as u64) }
+}
+
+/// One wake: fetch, maybe update, render. Errors carry the [`ErrorKind`]
+/// shown to the user as context.
+fn run(
+    nvs: EspDefaultNvsPartition,
+    net: NetParts,
+    epd_pins: &mut Option<EpdHardwarePins>,
+) -> anyhow::Result<()> {
+    let mut nvs_config = config::open(nvs.clone())?;
+    let server = config::load_server_config(&nvs_config).context(ErrorKind::NotConfigured)?;
+    let wifi = config::load_wifi_settings(&nvs_config).context(ErrorKind::NotConfigured)?;
+    let layout = config::load_layout(&nvs_config).context(ErrorKind::NotConfigured)?;
+
+    let mut session =
+        NetworkSession::connect(net, nvs.clone(), &wifi).context(ErrorKind::NoWifi)?;
+    session.configure_tls(&server);
+    match session.rssi() {
+        Ok(rssi) => info!("Wifi RSSI: {rssi} dBm"),
+        Err(err) => warn!("Wifi RSSI unavailable: {err:#}"),
+    }
+    clock::set_timezone(&config::load_timezone(&nvs_config)?);
+    if let Err(err) = clock::sync(SNTP_TIMEOUT) {
+        warn!("{err:#}, keeping the RTC's time");
+    }
+
+    // A freshly installed image has to render once before it is confirmed,
+    // so don't let a 304 skip that.
+    let unverified = ota::running_image_unverified()?;
+    let validators = if unverified {
+        Validators::default()
+    } else {
+        config::load_validators(&nvs_config)?
+    };
+    let outcome = fetch_schedule(&mut session, &server, &validators);
+
+    if !unverified {
+        if let Some(manifest_server) = config::load_ota_config(&nvs_config)? {
+            match ota::update_if_available(&mut session, &manifest_server) {
+                Ok(true) => {
+                    session.shutdown()?;
+                    info!("Restarting into the new firmware");
+                    restart();
+                }
+                Ok(false) => {}
+                Err(err) => warn!("OTA: update check failed: {err:#}"),
+            }
+        }
+    }
+    session.shutdown()?;
+
+    let now = clock::now_hours();
+    if now.is_none() {
+        warn!("Clock not set, leaving the now-line out");
+    }
+
+    // Skip parsing, rendering and the (slow, power hungry) refresh when the
+    // server says the schedule on the panel is still current.
+    let outcome = outcome.map_err(|err| {
+        let kind = fetch_error_kind(&err);
+        err.context(kind)
+    });
+    let size = panel::drawing_size(
+        epd_pins.as_ref().context("EPD pins already in use")?.panel,
+        ROTATION,
+    );
+    let (validators, shown) = match outcome? {
+        FetchOutcome::NotModified => match epd::load_refresh_state() {
+            Some(shown) if same_frame(&layout, size, shown.nowline_time, now)? => {
+                info!("Schedule not modified, keeping the current frame");
+                return Ok(());
+            }
+            // Something drawn from the clock changed, or the panel state was
+            // lost with power.
+            shown => (None, shown),
+        },
+        FetchOutcome::Updated { body, validators } => {
+            info!("Fetched schedule, {} bytes", body.len());
+            serde_json::from_slice::<serde_json::Value>(&body).context(ErrorKind::BadPayload)?;
+            (Some(validators), None)
+        }
+    };
+
+    let pins = epd_pins.take().context("EPD pins already in use")?;
+    if let Err(err) = render_schedule(pins, shown, &layout, now) {
+        error!("Rendering failed: {err:#}");
+        ota::reject_unverified_image()?;
+        return Err(err);
+    }
+    ota::confirm_running_image()?;
+
+    if let Some(validators) = validators {
+        config::store_validators(&mut nvs_config, &validators)?;
+    }
+
+    Ok(())
+}
+
+/// Whether a failed fetch reached the server: it may have answered with an
+/// error status or broken redirects, or not at all.
+fn fetch_error_kind(err: &anyhow::Error) -> ErrorKind {
+    if let Some(FetchError::Status(status)) = err.downcast_ref::<FetchError>() {
+        ErrorKind::ServerError(Some(*status))
+    } else if err.downcast_ref::<HttpError>().is_some() {
+        ErrorKind::ServerError(None)
+    } else {
+        ErrorKind::ServerUnreachable
+    }
+}
+
+fn show_error(pins: EpdHardwarePins, err: &anyhow::Error) -> anyhow::Result<()> {
+    let kind = err
+        .downcast_ref::<ErrorKind>()
+        .copied()
+        .unwrap_or(ErrorKind::Internal);
+    let detail = err.root_cause().to_string();
+    let device_id = config::device_id();
+
+    let mut epd = EpdSession::open(pins, ROTATION, DEFAULT_SPI_CLOCK)?;
+    let size = epd.size();
+    epd.render(|canvas| {
+        ErrorScreen::new(
+            Point::zero(),
+            size,
+            kind,
+            &detail,
+            &device_id,
+            unif_color_converter,
+        )
+        .with_theme(Theme::for_size(size))
+        .draw(canvas)?;
+        Ok(())
+    })?;
+    // The schedule is gone from the panel, the next success redraws it all.
+    epd::clear_refresh_state();
+
+    epd.finish()
+}
+
+/// Whether the frame drawn at `now` looks exactly like the one drawn at
+/// `shown`, e.g. the now-line is still on the same pixel row. Compares
+/// hashes of the pixels, the panel isn't touched.
+fn same_frame(
+    layout: &Layout,
+    size: Size,
+    shown: Option<f32>,
+    now: Option<f32>,
+) -> anyhow::Result<bool> {
+    Ok(frame_hash(layout, size, shown)? == frame_hash(layout, size, now)?)
+}
 
-    let battery_level_percent = 19; // Example battery level
+fn frame_hash(layout: &Layout, size: Size, now: Option<f32>) -> anyhow::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut sink = |Pixel(point, color): Pixel<TriColor>| {
+        let color: u8 = match color {
+            TriColor::Black => 0,
+            TriColor::White => 1,
+            TriColor::Chromatic => 2,
+        };
+        (point.x, point.y, color).hash(&mut hasher);
+    };
+    draw_schedule(&mut Canvas::new(&mut sink, size), layout, now)?;
+    Ok(hasher.finish())
+}
 
-    // // Draw battery indicator at the very bottom
-    // BatteryIndicator::new(
-    //     Point::new(0, 0),
-    //     Size::new(display_width, battery_bar_height),
-    // )
-    // .draw(display.as_mut(), battery_level_percent)?;
+/// Renders the schedule in the configured layout, with the now-line at
+/// `now`. With `shown` describing the frame already on the panel, only the
+/// areas that changed since are refreshed where possible.
+fn render_schedule(
+    epd_pins: EpdHardwarePins,
+    shown: Option<RefreshState>,
+    layout: &Layout,
+    now: Option<f32>,
+) -> anyhow::Result<()> {
+    let mut epd = EpdSession::open(epd_pins, ROTATION, DEFAULT_SPI_CLOCK)?;
 
-    epd.update_and_display_frame(&mut spidd, display.buffer(), &mut delay)?;
+    // Only the grid knows which areas a later time changes
+    let refresh = match layout {
+        Layout::Grid => {
+            let table = grid_table(epd.size(), now)?;
+            for (i, issue) in table.issues() {
+                warn!("Event {i} {issue}");
+            }
+            match shown {
+                Some(shown) => grid_refresh(&mut epd, &table, shown, now),
+                None => Refresh::Full,
+            }
+        }
+        _ => Refresh::Full,
+    };
+    epd.render_with(|canvas| draw_schedule(canvas, layout, now), &refresh)?;
 
-    info!("Frame updated and displayed");
+    epd::store_refresh_state(RefreshState {
+        partials_since_full: match (&refresh, shown) {
+            (Refresh::Partial(_), Some(shown)) => shown.partials_since_full + 1,
+            (Refresh::None, Some(shown)) => shown.partials_since_full,
+            _ => 0,
+        },
+        nowline_time: now,
+    });
 
-    delay.delay_ms(1000);
-    epd.sleep(&mut spidd, &mut delay)?;
+    epd.finish()
+}
 
+/// Draws the schedule in `layout`, with the now-line at `now`.
+fn draw_schedule(canvas: &mut Canvas<'_>, layout: &Layout, now: Option<f32>) -> anyhow::Result<()> {
+    let size = canvas.size();
+    let theme = Theme::for_size(size);
+    match layout {
+        Layout::Grid => grid_table(size, now)?.draw(canvas)?,
+        // Without a clock, as if the day had not started
+        Layout::Room { name } => RoomStatus::new(
+            Point::zero(),
+            size,
+            name,
+            &EVENTS,
+            TODAY,
+            now.unwrap_or_default(),
+            unif_color_converter,
+        )
+        .with_theme(theme)
+        .draw(canvas)?,
+        Layout::Agenda => AgendaList::new(Point::zero(), size, &EVENTS, unif_color_converter)
+            .with_theme(theme)
+            .draw(canvas)?,
+        Layout::Custom { screen, logo } => {
+            let area = Rectangle::new(Point::zero(), size);
+            let data = ScreenData {
+                events: &EVENTS,
+                dates: &["01.01.2025", "02.01.2025", "03.01.2025"],
+                today: TODAY,
+                now: now.unwrap_or_default(),
+                room_name: "",
+                battery_percent: 19,
+                logo: logo.as_deref(),
+                booking_url: None,
+            };
+            // Components borrow the canvas type, so the tree is built per draw
+            screen
+                .build(area, &data, theme, unif_color_converter)?
+                .draw_in(canvas, area)?;
+        }
+    }
     Ok(())
 }
+
+type GridTable = ScheduleTable<'static, fn(UnifiedColor) -> TriColor, TriColor>;
+
+/// The three-day grid filling `size`, with the now-line at `now`.
+fn grid_table(size: Size, now: Option<f32>) -> anyhow::Result<GridTable> {
+    let mut table = ScheduleTable::builder(Point::zero(), size, unif_color_converter as fn(_) -> _)
+        .header_height(40)
+        .time_col_width(80)
+        .dates(&["01.01.2025", "02.01.2025", "03.01.2025"])
+        .time_grid(TimeGrid::hours(6, 18, SlotLength::Min60)) // 6:00 to 18:00, one row per hour
+        .events(&EVENTS)
+        .theme(Theme::for_size(size));
+    if let Some(now) = now {
+        table = table.now(now);
+    }
+    Ok(table.build()?)
+}
+
+/// Refresh for moving the now-line from where `shown` drew it to `now`.
+fn grid_refresh(
+    epd: &mut EpdSession,
+    table: &GridTable,
+    shown: RefreshState,
+    now: Option<f32>,
+) -> Refresh {
+    let mut dirty = DirtyRegions::new();
+    for time in [shown.nowline_time, now].into_iter().flatten() {
+        dirty.add(table.nowline_area(time));
+    }
+    dirty.align(epd.panel().partial_step());
+    dirty.clip(Rectangle::new(Point::zero(), epd.size()));
+    RefreshPolicy::default().plan(
+        &dirty,
+        epd.size(),
+        epd.panel().supports_partial(),
+        shown.partials_since_full,
+    )
+}
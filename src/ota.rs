@@ -0,0 +1,109 @@
+use embedded_svc::io::Write;
+use esp_idf_svc::ota::{EspOta, SlotState};
+use firmware_core::http::ServerConfig;
+use firmware_core::ota::{ImageVerifier, Manifest, Version};
+use firmware_core::transport::ScheduleTransport;
+use log::{error, info, warn};
+
+use crate::wifilib::NetworkSession;
+
+/// Version of the running firmware, compared against the update manifest.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Size of the OTA slot the next update would be written to.
+fn update_slot_size() -> anyhow::Result<u32> {
+    // SAFETY: returns a pointer into the static partition table, or null.
+    let partition = unsafe { esp_idf_sys::esp_ota_get_next_update_partition(std::ptr::null()) };
+    if partition.is_null() {
+        return Err(anyhow::anyhow!("No OTA update partition"));
+    }
+    // SAFETY: checked for null above.
+    Ok(unsafe { (*partition).size })
+}
+
+/// Checks the manifest at `manifest_server` and, when it offers a newer
+/// version, writes and verifies it into the inactive slot and makes it the
+/// boot slot. The image is fetched with the manifest server's trust anchor.
+/// Returns `true` when the device should restart into the new firmware.
+pub fn update_if_available(
+    session: &mut NetworkSession,
+    manifest_server: &ServerConfig,
+) -> anyhow::Result<bool> {
+    session.configure_tls(manifest_server);
+    let request = manifest_server.get("application/json")?;
+    let response = session.send(&request, &[])?;
+    if response.status != 200 {
+        return Err(anyhow::anyhow!(
+            "Update manifest request failed with status {}",
+            response.status
+        ));
+    }
+
+    let manifest = Manifest::parse(&response.body)?;
+    let current: Version = CURRENT_VERSION.parse()?;
+    if !manifest.is_update_for(&current, update_slot_size()?)? {
+        info!("OTA: firmware {current} is up to date");
+        return Ok(false);
+    }
+    info!(
+        "OTA: updating {current} -> {} ({} bytes)",
+        manifest.version, manifest.size
+    );
+
+    let mut ota = EspOta::new()?;
+    let mut update = ota.initiate_update()?;
+    let mut verifier = ImageVerifier::new(&manifest);
+
+    let image_server = ServerConfig::new(&manifest.url)?
+        .with_ca(manifest_server.ca.clone())
+        .with_timeout(manifest_server.timeout);
+    let written = session
+        .download(&image_server, |chunk| {
+            verifier.update(chunk)?;
+            update.write_all(chunk)?;
+            Ok(())
+        })
+        .and_then(|()| Ok(verifier.finish()?));
+
+    match written {
+        Ok(()) => {
+            // Sets the new slot as boot partition, pending verification.
+            update.complete()?;
+            info!("OTA: {} installed, restart required", manifest.version);
+            Ok(true)
+        }
+        Err(err) => {
+            // What went wrong with the image matters more
+            if let Err(abort_err) = update.abort() {
+                warn!("OTA: aborting the update failed: {abort_err}");
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Whether this image was just installed and has not proven itself yet.
+pub fn running_image_unverified() -> anyhow::Result<bool> {
+    Ok(EspOta::new()?.get_running_slot()?.state == SlotState::Unverified)
+}
+
+/// Keeps the running image, cancelling the pending rollback.
+pub fn confirm_running_image() -> anyhow::Result<()> {
+    let mut ota = EspOta::new()?;
+    if ota.get_running_slot()?.state == SlotState::Unverified {
+        ota.mark_running_slot_valid()?;
+        info!("OTA: running image marked valid");
+    }
+    Ok(())
+}
+
+/// Rolls back to the previous image if the running one is still unverified.
+/// Does not return in that case.
+pub fn reject_unverified_image() -> anyhow::Result<()> {
+    let mut ota = EspOta::new()?;
+    if ota.get_running_slot()?.state == SlotState::Unverified {
+        error!("OTA: new image failed, rolling back");
+        return Err(ota.mark_running_slot_invalid_and_reboot().into());
+    }
+    Ok(())
+}
@@ -0,0 +1,436 @@
+use core::convert::Infallible;
+
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use epd_waveshare::color::{Color, ColorType, TriColor};
+use epd_waveshare::epd2in9_v2::{Display2in9, Epd2in9};
+use epd_waveshare::epd4in2::{Display4in2, Epd4in2};
+use epd_waveshare::epd7in5b_v3::{Display7in5, Epd7in5};
+use epd_waveshare::graphics::{Display, DisplayRotation};
+use epd_waveshare::prelude::{QuickRefresh, RefreshLut, WaveshareDisplay};
+use esp_idf_hal::delay::Delay;
+use esp_idf_hal::gpio::{AnyInputPin, AnyOutputPin, Input, Output, PinDriver};
+use esp_idf_hal::spi::{SpiDeviceDriver, SpiDriver};
+
+pub use firmware_core::board::PanelKind;
+
+pub type Spi = SpiDeviceDriver<'static, SpiDriver<'static>>;
+pub type Busy = PinDriver<'static, AnyInputPin, Input>;
+pub type Out = PinDriver<'static, AnyOutputPin, Output>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorCapability {
+    BlackWhite,
+    TriColor,
+}
+
+/// Drawing surface handed to rendering code. Components always draw
+/// [`TriColor`], panels without a chromatic plane render it as black.
+pub struct Canvas<'a> {
+    sink: &'a mut dyn FnMut(Pixel<TriColor>),
+    size: Size,
+}
+
+impl<'a> Canvas<'a> {
+    /// Canvas handing every pixel to `sink`, e.g. to look at a frame
+    /// without a panel.
+    pub fn new(sink: &'a mut dyn FnMut(Pixel<TriColor>), size: Size) -> Self {
+        Canvas { sink, size }
+    }
+}
+
+impl DrawTarget for Canvas<'_> {
+    type Color = TriColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        pixels.into_iter().for_each(&mut self.sink);
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Canvas<'_> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+pub trait Panel {
+    fn kind(&self) -> PanelKind;
+    /// Drawing size, rotation already applied.
+    fn size(&self) -> Size;
+    fn colors(&self) -> ColorCapability;
+    /// Whether [`Panel::update_partial`] uses the quick refresh instead of
+    /// a full one.
+    fn supports_partial(&self) -> bool;
+    /// Partial windows are whole bytes of the frame buffer, i.e. 8 pixels
+    /// along the native x axis, wherever rotation puts it.
+    fn partial_step(&self) -> Size;
+
+    /// Draws into the frame buffer, nothing is sent to the panel yet.
+    fn draw(
+        &mut self,
+        scene: &mut dyn FnMut(&mut Canvas<'_>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()>;
+
+    /// Wakes the controller up, required after [`Panel::sleep`].
+    fn init(&mut self) -> anyhow::Result<()>;
+    /// Sends the whole frame buffer and refreshes the panel.
+    fn update_full(&mut self) -> anyhow::Result<()>;
+    /// Refreshes only `areas` (drawing coordinates), all at once with the
+    /// quick refresh. Falls back to a full update on panels without one.
+    fn update_partial(&mut self, areas: &[Rectangle]) -> anyhow::Result<()>;
+    /// Blanks the panel itself, the frame buffer is left untouched.
+    fn clear(&mut self) -> anyhow::Result<()>;
+    fn sleep(&mut self) -> anyhow::Result<()>;
+}
+
+/// Native pixel format of a frame buffer.
+pub trait NativeColor: ColorType + PixelColor {
+    const CAPABILITY: ColorCapability;
+    fn from_tri(color: TriColor) -> Self;
+}
+
+impl NativeColor for TriColor {
+    const CAPABILITY: ColorCapability = ColorCapability::TriColor;
+
+    fn from_tri(color: TriColor) -> Self {
+        color
+    }
+}
+
+impl NativeColor for Color {
+    const CAPABILITY: ColorCapability = ColorCapability::BlackWhite;
+
+    fn from_tri(color: TriColor) -> Self {
+        match color {
+            TriColor::White => Color::White,
+            TriColor::Black | TriColor::Chromatic => Color::Black,
+        }
+    }
+}
+
+/// epd-waveshare frame buffer, whatever its size and pixel format.
+pub trait FrameBuffer:
+    Default + OriginDimensions + DrawTarget<Color = Self::Native, Error = Infallible>
+{
+    type Native: NativeColor;
+    /// Size in the panel's native orientation.
+    const NATIVE_SIZE: Size;
+
+    fn buffer(&self) -> &[u8];
+    fn rotation(&self) -> DisplayRotation;
+    fn set_rotation(&mut self, rotation: DisplayRotation);
+}
+
+impl<const W: u32, const H: u32, const BWRBIT: bool, const N: usize, C> FrameBuffer
+    for Display<W, H, BWRBIT, N, C>
+where
+    C: NativeColor,
+{
+    type Native = C;
+    const NATIVE_SIZE: Size = Size::new(W, H);
+
+    fn buffer(&self) -> &[u8] {
+        Display::buffer(self)
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        Display::rotation(self)
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        Display::set_rotation(self, rotation)
+    }
+}
+
+/// An epd-waveshare driver, with its quick refresh where it has one.
+pub trait Driver: WaveshareDisplay<Spi, Busy, Out, Out, Delay> {
+    const QUICK_REFRESH: bool;
+
+    /// Shows `new` with the quick waveform, which only drives the pixels
+    /// that differ from `old`. Only called when [`Self::QUICK_REFRESH`] is
+    /// set.
+    fn quick_refresh(
+        &mut self,
+        spi: &mut Spi,
+        delay: &mut Delay,
+        old: &[u8],
+        new: &[u8],
+    ) -> anyhow::Result<()>;
+}
+
+fn quick_refresh<E>(
+    epd: &mut E,
+    spi: &mut Spi,
+    delay: &mut Delay,
+    old: &[u8],
+    new: &[u8],
+) -> anyhow::Result<()>
+where
+    E: WaveshareDisplay<Spi, Busy, Out, Out, Delay> + QuickRefresh<Spi, Busy, Out, Out, Delay>,
+{
+    epd.set_lut(spi, delay, Some(RefreshLut::Quick))?;
+    epd.update_old_frame(spi, old, delay)?;
+    epd.update_new_frame(spi, new, delay)?;
+    Ok(epd.display_new_frame(spi, delay)?)
+}
+
+impl Driver for Epd7in5<Spi, Busy, Out, Out, Delay> {
+    const QUICK_REFRESH: bool = false;
+
+    fn quick_refresh(
+        &mut self,
+        _spi: &mut Spi,
+        _delay: &mut Delay,
+        _old: &[u8],
+        _new: &[u8],
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("the 7.5\" B v3 panel has no quick refresh")
+    }
+}
+
+impl Driver for Epd4in2<Spi, Busy, Out, Out, Delay> {
+    const QUICK_REFRESH: bool = true;
+
+    fn quick_refresh(
+        &mut self,
+        spi: &mut Spi,
+        delay: &mut Delay,
+        old: &[u8],
+        new: &[u8],
+    ) -> anyhow::Result<()> {
+        quick_refresh(self, spi, delay, old, new)
+    }
+}
+
+impl Driver for Epd2in9<Spi, Busy, Out, Out, Delay> {
+    const QUICK_REFRESH: bool = true;
+
+    fn quick_refresh(
+        &mut self,
+        spi: &mut Spi,
+        delay: &mut Delay,
+        old: &[u8],
+        new: &[u8],
+    ) -> anyhow::Result<()> {
+        quick_refresh(self, spi, delay, old, new)
+    }
+}
+
+/// Any epd-waveshare driver together with its frame buffer.
+pub struct EpdPanel<E, D> {
+    kind: PanelKind,
+    epd: E,
+    display: Box<D>,
+    spi: Spi,
+    delay: Delay,
+}
+
+impl<E, D> EpdPanel<E, D>
+where
+    E: Driver,
+    D: FrameBuffer,
+{
+    fn new(
+        kind: PanelKind,
+        mut spi: Spi,
+        busy: Busy,
+        dc: Out,
+        rst: Out,
+        rotation: DisplayRotation,
+    ) -> anyhow::Result<Self> {
+        let mut delay = Delay::new(100);
+        let epd = E::new(&mut spi, busy, dc, rst, &mut delay, None)?;
+
+        let mut display = Box::new(D::default());
+        display.set_rotation(rotation);
+
+        Ok(EpdPanel {
+            kind,
+            epd,
+            display,
+            spi,
+            delay,
+        })
+    }
+}
+
+impl<E, D> Panel for EpdPanel<E, D>
+where
+    E: Driver,
+    D: FrameBuffer,
+{
+    fn kind(&self) -> PanelKind {
+        self.kind
+    }
+
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+
+    fn colors(&self) -> ColorCapability {
+        D::Native::CAPABILITY
+    }
+
+    fn supports_partial(&self) -> bool {
+        E::QUICK_REFRESH
+    }
+
+    fn partial_step(&self) -> Size {
+        match self.display.rotation() {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(8, 1),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(1, 8),
+        }
+    }
+
+    fn draw(
+        &mut self,
+        scene: &mut dyn FnMut(&mut Canvas<'_>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let size = self.display.size();
+        let display = &mut *self.display;
+        let mut sink = |Pixel(point, color): Pixel<TriColor>| {
+            let _ = Pixel(point, D::Native::from_tri(color)).draw(display);
+        };
+        scene(&mut Canvas {
+            sink: &mut sink,
+            size,
+        })
+    }
+
+    fn init(&mut self) -> anyhow::Result<()> {
+        Ok(self.epd.wake_up(&mut self.spi, &mut self.delay)?)
+    }
+
+    fn update_full(&mut self) -> anyhow::Result<()> {
+        if E::QUICK_REFRESH {
+            self.epd
+                .set_lut(&mut self.spi, &mut self.delay, Some(RefreshLut::Full))?;
+        }
+        Ok(self.epd.update_and_display_frame(
+            &mut self.spi,
+            self.display.buffer(),
+            &mut self.delay,
+        )?)
+    }
+
+    fn update_partial(&mut self, areas: &[Rectangle]) -> anyhow::Result<()> {
+        if !E::QUICK_REFRESH {
+            return self.update_full();
+        }
+
+        // Panel RAM doesn't survive power-off, so the whole frame goes out
+        // every time. As the old frame it has the dirty windows inverted:
+        // the windows are driven to their new content whatever they showed
+        // before, everything else is left alone.
+        let native = D::NATIVE_SIZE;
+        let rotation = self.display.rotation();
+        let new = self.display.buffer();
+        let mut old = new.to_vec();
+        for &area in areas {
+            let window = native_window(area, rotation, native);
+            invert_window(&mut old, new, native.width, window);
+        }
+        self.epd
+            .quick_refresh(&mut self.spi, &mut self.delay, &old, new)
+    }
+
+    fn clear(&mut self) -> anyhow::Result<()> {
+        Ok(self.epd.clear_frame(&mut self.spi, &mut self.delay)?)
+    }
+
+    fn sleep(&mut self) -> anyhow::Result<()> {
+        Ok(self.epd.sleep(&mut self.spi, &mut self.delay)?)
+    }
+}
+
+/// Maps `area` from rotated drawing coordinates into the panel's native
+/// orientation, widened to whole bytes horizontally and clipped to the panel.
+fn native_window(area: Rectangle, rotation: DisplayRotation, native: Size) -> Rectangle {
+    let Some(bottom_right) = area.bottom_right() else {
+        return Rectangle::zero();
+    };
+    let (w, h) = (native.width as i32, native.height as i32);
+    let rotate = |p: Point| match rotation {
+        DisplayRotation::Rotate0 => p,
+        DisplayRotation::Rotate90 => Point::new(w - 1 - p.y, p.x),
+        DisplayRotation::Rotate180 => Point::new(w - 1 - p.x, h - 1 - p.y),
+        DisplayRotation::Rotate270 => Point::new(p.y, h - 1 - p.x),
+    };
+    let (a, b) = (rotate(area.top_left), rotate(bottom_right));
+
+    let x0 = (a.x.min(b.x).max(0) / 8) * 8;
+    let x1 = ((a.x.max(b.x).min(w - 1) / 8) + 1) * 8;
+    let y0 = a.y.min(b.y).max(0);
+    let y1 = a.y.max(b.y).min(h - 1) + 1;
+    if x1 <= x0 || y1 <= y0 {
+        return Rectangle::zero();
+    }
+
+    Rectangle::new(
+        Point::new(x0, y0),
+        Size::new((x1.min(w) - x0) as u32, (y1 - y0) as u32),
+    )
+}
+
+/// Sets a byte aligned window of the 1 bit per pixel buffer `old` to the
+/// inverse of `new`.
+fn invert_window(old: &mut [u8], new: &[u8], native_width: u32, window: Rectangle) {
+    let stride = native_width.div_ceil(8) as usize;
+    let x = window.top_left.x as usize / 8;
+    let bytes = window.size.width.div_ceil(8) as usize;
+
+    let top = window.top_left.y as usize;
+    for row in top..top + window.size.height as usize {
+        let range = row * stride + x..row * stride + x + bytes;
+        for (old, new) in old[range.clone()].iter_mut().zip(&new[range]) {
+            *old = !*new;
+        }
+    }
+}
+
+/// Drawing size of a `kind` panel turned by `rotation`, known without
+/// opening it.
+pub fn drawing_size(kind: PanelKind, rotation: DisplayRotation) -> Size {
+    let native = match kind {
+        PanelKind::Epd7in5bV3 => Display7in5::NATIVE_SIZE,
+        PanelKind::Epd4in2 => Display4in2::NATIVE_SIZE,
+        PanelKind::Epd2in9V2 => Display2in9::NATIVE_SIZE,
+    };
+    match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => native,
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+            Size::new(native.height, native.width)
+        }
+    }
+}
+
+/// Connects to the panel of the given kind.
+pub fn open(
+    kind: PanelKind,
+    spi: Spi,
+    busy: Busy,
+    dc: Out,
+    rst: Out,
+    rotation: DisplayRotation,
+) -> anyhow::Result<Box<dyn Panel>> {
+    Ok(match kind {
+        PanelKind::Epd7in5bV3 => Box::new(EpdPanel::<
+            Epd7in5<Spi, Busy, Out, Out, Delay>,
+            Display7in5,
+        >::new(kind, spi, busy, dc, rst, rotation)?),
+        PanelKind::Epd4in2 => Box::new(
+            EpdPanel::<Epd4in2<Spi, Busy, Out, Out, Delay>, Display4in2>::new(
+                kind, spi, busy, dc, rst, rotation,
+            )?,
+        ),
+        PanelKind::Epd2in9V2 => Box::new(EpdPanel::<
+            Epd2in9<Spi, Busy, Out, Out, Delay>,
+            Display2in9,
+        >::new(kind, spi, busy, dc, rst, rotation)?),
+    })
+}
@@ -1,61 +1,385 @@
 use embedded_svc::http::client::Client as HttpClient;
-use esp_idf_svc::http::client::EspHttpConnection;
+use embedded_svc::io::Write;
+use esp_idf_svc::http::client::{
+    Configuration as HttpConfiguration, EspHttpConnection, FollowRedirectsPolicy,
+};
 use esp_idf_svc::http::Method;
-use embedded_svc::utils::io;
+use esp_idf_svc::ipv4;
+use esp_idf_svc::netif::{EspNetif, NetifConfiguration, NetifStack};
 use esp_idf_svc::nvs::EspDefaultNvsPartition;
-use esp_idf_svc::{
-    wifi::{
-        AuthMethod, BlockingWifi, ClientConfiguration, Configuration as WifiConfiguration, EspWifi,
-    },
+use esp_idf_svc::tls::X509;
+use esp_idf_svc::wifi::{
+    AuthMethod, BlockingWifi, ClientConfiguration, Configuration as WifiConfiguration, EspWifi,
+    WifiDriver,
 };
-use log::{error, info};
+use firmware_core::http::{self, CaSource, RequestSpec, Response, ServerConfig, DEFAULT_TIMEOUT};
+use firmware_core::transport::ScheduleTransport;
+use firmware_core::wifi::{CachedAp, ConnectPlan, Network, StaticIp, WifiSettings};
+use log::{info, warn};
+use std::ptr::{addr_of, addr_of_mut};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::epd_pins::NetParts;
 
-const SSID: &str = "Mi 9 SE";
-const PASS: &str = "passpass";
+/// Survives deep sleep (not power loss), so a wake can reconnect to the last
+/// AP without scanning.
+#[link_section = ".rtc.data"]
+static mut RTC_AP: RtcAp = RtcAp::EMPTY;
 
-pub fn getRequest(NetParts { modem, sysloop }: NetParts, nvs: EspDefaultNvsPartition) -> anyhow::Result<String> {
-    let mut wifi = BlockingWifi::wrap(
-        // TODO: handle wifi connection error
-        EspWifi::new(modem, sysloop.clone(), Some(nvs))?,
-        sysloop,
-    )?;
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct RtcAp {
+    magic: u32,
+    network: u8,
+    channel: u8,
+    bssid: [u8; 6],
+}
+
+impl RtcAp {
+    const MAGIC: u32 = 0x5246_4150;
+    const EMPTY: Self = RtcAp {
+        magic: 0,
+        network: 0,
+        channel: 0,
+        bssid: [0; 6],
+    };
+}
+
+fn load_cached_ap() -> Option<CachedAp> {
+    // SAFETY: RTC_AP is only accessed from the main task.
+    let rtc = unsafe { addr_of!(RTC_AP).read() };
+    (rtc.magic == RtcAp::MAGIC).then_some(CachedAp {
+        network: rtc.network.into(),
+        bssid: rtc.bssid,
+        channel: rtc.channel,
+    })
+}
 
-    let wifi_configuration: WifiConfiguration = WifiConfiguration::Client(ClientConfiguration {
-        ssid: SSID
+fn store_cached_ap(ap: Option<CachedAp>) {
+    let rtc = match ap {
+        Some(ap) => RtcAp {
+            magic: RtcAp::MAGIC,
+            network: ap.network as u8,
+            channel: ap.channel,
+            bssid: ap.bssid,
+        },
+        None => RtcAp::EMPTY,
+    };
+    // SAFETY: RTC_AP is only accessed from the main task.
+    unsafe { addr_of_mut!(RTC_AP).write(rtc) };
+}
+
+/// AP the station is currently associated with.
+fn current_ap(network: usize) -> Option<CachedAp> {
+    let mut info = esp_idf_sys::wifi_ap_record_t::default();
+    // SAFETY: `info` is a valid out-parameter for the duration of the call.
+    let ok = unsafe { esp_idf_sys::esp_wifi_sta_get_ap_info(&mut info) } == esp_idf_sys::ESP_OK;
+    ok.then_some(CachedAp {
+        network,
+        bssid: info.bssid,
+        channel: info.primary,
+    })
+}
+
+fn client_configuration(
+    network: &Network,
+    ap: Option<([u8; 6], u8)>,
+) -> anyhow::Result<WifiConfiguration> {
+    Ok(WifiConfiguration::Client(ClientConfiguration {
+        ssid: network
+            .ssid
+            .as_str()
             .try_into()
-            .expect("Could not parse the given SSID into WiFi config"),
-        bssid: None,
-        auth_method: AuthMethod::WPA2Personal,
-        password: PASS
+            .map_err(|_| anyhow::anyhow!("SSID '{}' is too long", network.ssid))?,
+        bssid: ap.map(|(bssid, _)| bssid),
+        auth_method: if network.password.is_empty() {
+            AuthMethod::None
+        } else {
+            AuthMethod::WPA2Personal
+        },
+        password: network
+            .password
+            .as_str()
             .try_into()
-            .expect("Could not parse the given password into WiFi config"),
-        channel: None,
+            .map_err(|_| anyhow::anyhow!("Password for '{}' is too long", network.ssid))?,
+        channel: ap.map(|(_, channel)| channel),
         ..Default::default()
-    });
-    wifi.set_configuration(&wifi_configuration)?;
-    wifi.start()?;
-    wifi.connect()?;
-    wifi.wait_netif_up()?;
-    info!("Wifi connected");
-
-    let mut client = HttpClient::wrap(EspHttpConnection::new(&Default::default())?);
-    let headers = [("accept", "application/json")];
-    let request = client.request(Method::Get, "http://google.com:80", &headers)?;
-    let mut response = request.submit()?;
-    let status = response.status();
-    info!("Response status: {}", status);
-    let mut bytes = [0; 1024]; // Buffer size of 1024 bytes
-    let readBytes = response.read(&mut bytes)?;
-    info!("Read {} bytes", bytes.len());
-    let body = String::from_utf8(bytes[0..readBytes].to_vec());
-    info!("Response body: {:?}", body);
-
-    if body.is_err() {
-        error!("Failed to read response body: {:?}", body);
-        return Err(anyhow::anyhow!("Failed to read response body"));
-    }
-
-    Ok(body?)
+    }))
+}
+
+fn station_netif(static_ip: Option<StaticIp>) -> anyhow::Result<EspNetif> {
+    let Some(static_ip) = static_ip else {
+        return Ok(EspNetif::new(NetifStack::Sta)?);
+    };
+
+    Ok(EspNetif::new_with_conf(&NetifConfiguration {
+        ip_configuration: Some(ipv4::Configuration::Client(
+            ipv4::ClientConfiguration::Fixed(ipv4::ClientSettings {
+                ip: static_ip.ip,
+                subnet: ipv4::Subnet {
+                    gateway: static_ip.gateway,
+                    mask: ipv4::Mask(static_ip.prefix_len),
+                },
+                dns: static_ip.dns,
+                secondary_dns: None,
+            }),
+        )),
+        ..NetifConfiguration::wifi_default_client()
+    })?)
+}
+
+/// Associates and waits for an address, giving up after `timeout`.
+fn connect_within(
+    wifi: &mut BlockingWifi<EspWifi<'static>>,
+    timeout: Duration,
+) -> Result<(), esp_idf_sys::EspError> {
+    let deadline = Instant::now() + timeout;
+    wifi.wifi_mut().connect()?;
+    wifi.wifi_wait_while(
+        || wifi.wifi().is_connected().map(|connected| !connected),
+        Some(timeout),
+    )?;
+    wifi.ip_wait_while(
+        || wifi.wifi().is_up().map(|up| !up),
+        Some(deadline.saturating_duration_since(Instant::now())),
+    )
+}
+
+/// One Wi-Fi connection shared by every request made during a wake cycle
+/// (schedule, time, telemetry, OTA check).
+pub struct NetworkSession {
+    wifi: BlockingWifi<EspWifi<'static>>,
+    ca: CaSource,
+    timeout: Duration,
+}
+
+impl NetworkSession {
+    /// Brings Wi-Fi up, walking the configured networks with bounded retries
+    /// and backoff until `settings.policy.budget` is used up.
+    pub fn connect(
+        NetParts { modem, sysloop }: NetParts,
+        nvs: EspDefaultNvsPartition,
+        settings: &WifiSettings,
+    ) -> anyhow::Result<Self> {
+        let preferred = settings
+            .networks
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No Wi-Fi network configured"))?;
+
+        let driver = WifiDriver::new(modem, sysloop.clone(), Some(nvs))?;
+        let wifi = EspWifi::wrap_all(
+            driver,
+            station_netif(settings.static_ip)?,
+            EspNetif::new(NetifStack::Ap)?,
+        )?;
+        let mut wifi = BlockingWifi::wrap(wifi, sysloop)?;
+
+        wifi.set_configuration(&client_configuration(preferred, None)?)?;
+        wifi.start()?;
+
+        let started = Instant::now();
+        let mut plan = ConnectPlan::new(settings.policy, settings.networks.len(), load_cached_ap());
+        while let Some(attempt) = plan.next_attempt(started.elapsed()) {
+            thread::sleep(attempt.wait);
+
+            let network = &settings.networks[attempt.network];
+            let via = if attempt.ap.is_some() {
+                " (cached AP)"
+            } else {
+                ""
+            };
+            info!("Wifi: connecting to '{}'{via}", network.ssid);
+            wifi.set_configuration(&client_configuration(network, attempt.ap)?)?;
+
+            match connect_within(&mut wifi, attempt.timeout) {
+                Ok(()) => {
+                    store_cached_ap(current_ap(attempt.network));
+                    info!("Wifi connected in {:?}", started.elapsed());
+                    return Ok(NetworkSession {
+                        wifi,
+                        ca: CaSource::Bundle,
+                        timeout: DEFAULT_TIMEOUT,
+                    });
+                }
+                Err(err) => {
+                    warn!("Wifi: attempt failed: {err}");
+                    let _ = wifi.disconnect();
+                }
+            }
+        }
+
+        // The cached AP did not work either, scan again next time.
+        store_cached_ap(None);
+        Err(anyhow::anyhow!(
+            "No Wi-Fi network reachable within {:?}",
+            settings.policy.budget
+        ))
+    }
+
+    /// Uses the trust anchor and timeout of `config` for the following requests.
+    pub fn configure_tls(&mut self, config: &ServerConfig) {
+        self.ca = config.ca.clone();
+        self.timeout = config.timeout;
+    }
+
+    /// Signal strength of the current AP in dBm.
+    pub fn rssi(&self) -> anyhow::Result<i8> {
+        let mut info = esp_idf_sys::wifi_ap_record_t::default();
+        // SAFETY: `info` is a valid out-parameter for the duration of the call.
+        esp_idf_sys::esp!(unsafe { esp_idf_sys::esp_wifi_sta_get_ap_info(&mut info) })?;
+        Ok(info.rssi)
+    }
+
+    /// Disconnects and powers the radio down.
+    pub fn shutdown(mut self) -> anyhow::Result<()> {
+        self.wifi.disconnect()?;
+        self.wifi.stop()?;
+        info!("Wifi stopped");
+        Ok(())
+    }
+
+    fn connection(&self) -> anyhow::Result<EspHttpConnection> {
+        let server_certificate = match &self.ca {
+            // The driver keeps a 'static reference, one small leak per wake is fine.
+            CaSource::Pinned(pem) => Some(X509::pem_until_nul(Box::leak(
+                format!("{pem}\0").into_bytes().into_boxed_slice(),
+            ))),
+            CaSource::Bundle => None,
+        };
+
+        Ok(EspHttpConnection::new(&HttpConfiguration {
+            timeout: Some(self.timeout),
+            // Redirects are followed by hand so the token never leaves its origin.
+            follow_redirects_policy: FollowRedirectsPolicy::FollowNone,
+            crt_bundle_attach: match self.ca {
+                CaSource::Bundle => Some(esp_idf_sys::esp_crt_bundle_attach),
+                CaSource::Pinned(_) => None,
+            },
+            server_certificate,
+            ..Default::default()
+        })?)
+    }
+}
+
+/// Largest response body we are willing to buffer.
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+/// The ESP client can't enumerate headers, these are the ones we care about.
+const RESPONSE_HEADERS: [&str; 4] = ["ETag", "Last-Modified", "Location", "Content-Type"];
+
+impl NetworkSession {
+    /// Streams the body of a successful GET of `config.url` into `sink`,
+    /// following redirects. Used for payloads that don't fit into RAM.
+    pub fn download(
+        &mut self,
+        config: &ServerConfig,
+        mut sink: impl FnMut(&[u8]) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let mut request = config.get("application/octet-stream")?;
+        loop {
+            let response = self.perform(&request, &[], is_success, &mut sink)?;
+            if http::is_redirect(response.status) {
+                request = config.follow_redirect(
+                    &request,
+                    response.status,
+                    response.header("location"),
+                )?;
+                continue;
+            }
+            if !is_success(response.status) {
+                return Err(anyhow::anyhow!(
+                    "Server responded with status {}",
+                    response.status
+                ));
+            }
+            return Ok(());
+        }
+    }
+
+    /// Performs one request, passing the body to `sink` when `read_body`
+    /// accepts the status. The returned response has an empty body.
+    fn perform(
+        &mut self,
+        request: &RequestSpec,
+        body: &[u8],
+        read_body: impl Fn(u16) -> bool,
+        sink: &mut dyn FnMut(&[u8]) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Response> {
+        let mut client = HttpClient::wrap(self.connection()?);
+
+        let url = request.url.to_string();
+        let method = match request.method {
+            http::Method::Get => Method::Get,
+            http::Method::Head => Method::Head,
+            http::Method::Post => Method::Post,
+        };
+        let content_length = body.len().to_string();
+
+        let mut response = {
+            let mut headers: Vec<(&str, &str)> = request
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            if request.method == http::Method::Post {
+                headers.push(("Content-Length", &content_length));
+            }
+            let mut pending = client.request(method, &url, &headers)?;
+            pending.write_all(body)?;
+            pending.submit()?
+        };
+        let status = response.status();
+        info!("{:?} {} -> {}", request.method, url, status);
+
+        let headers = RESPONSE_HEADERS
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), response.header(name)?.to_string())))
+            .collect();
+
+        if request.method != http::Method::Head && read_body(status) {
+            let mut buf = [0; 1024];
+            loop {
+                let read = response.read(&mut buf)?;
+                if read == 0 {
+                    break;
+                }
+                sink(&buf[..read])?;
+            }
+        }
+
+        Ok(Response {
+            status,
+            headers,
+            body: Vec::new(),
+        })
+    }
+}
+
+fn is_success(status: u16) -> bool {
+    (200..300).contains(&status)
+}
+
+impl ScheduleTransport for NetworkSession {
+    type Error = anyhow::Error;
+
+    fn send(&mut self, request: &RequestSpec, body: &[u8]) -> anyhow::Result<Response> {
+        let mut collected = Vec::new();
+        let mut response = self.perform(
+            request,
+            body,
+            |status| status != 304 && !http::is_redirect(status),
+            &mut |chunk| {
+                if collected.len() + chunk.len() > MAX_BODY_LEN {
+                    return Err(anyhow::anyhow!(
+                        "Response body exceeds {MAX_BODY_LEN} bytes"
+                    ));
+                }
+                collected.extend_from_slice(chunk);
+                Ok(())
+            },
+        )?;
+        response.body = collected;
+        Ok(response)
+    }
 }